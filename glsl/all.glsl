@@ -0,0 +1,9 @@
+#pragma once
+
+// Every header of the glsl_compiler standard library.
+
+#include <glsl_compiler/hash.glsl>
+#include <glsl_compiler/random.glsl>
+#include <glsl_compiler/noise.glsl>
+#include <glsl_compiler/color.glsl>
+#include <glsl_compiler/packing.glsl>
@@ -0,0 +1,53 @@
+#pragma once
+
+// Color space conversions. Colors are linear Rec.709 / sRGB primaries unless a name says otherwise.
+
+vec3 srgb_to_linear(vec3 srgb) {
+    vec3 low = srgb / 12.92;
+    vec3 high = pow((srgb + 0.055) / 1.055, vec3(2.4));
+    return mix(low, high, greaterThan(srgb, vec3(0.04045)));
+}
+
+vec3 linear_to_srgb(vec3 rgb) {
+    vec3 low = rgb * 12.92;
+    vec3 high = 1.055 * pow(rgb, vec3(1.0 / 2.4)) - 0.055;
+    return mix(low, high, greaterThan(rgb, vec3(0.0031308)));
+}
+
+vec4 srgb_to_linear(vec4 srgb) {
+    return vec4(srgb_to_linear(srgb.rgb), srgb.a);
+}
+
+vec4 linear_to_srgb(vec4 rgba) {
+    return vec4(linear_to_srgb(rgba.rgb), rgba.a);
+}
+
+// Relative luminance of a linear color.
+float luminance(vec3 rgb) {
+    return dot(rgb, vec3(0.2126, 0.7152, 0.0722));
+}
+
+// Hue, saturation and value, all in [0, 1].
+vec3 rgb_to_hsv(vec3 rgb) {
+    vec4 k = vec4(0.0, -1.0 / 3.0, 2.0 / 3.0, -1.0);
+    vec4 p = rgb.g < rgb.b ? vec4(rgb.bg, k.wz) : vec4(rgb.gb, k.xy);
+    vec4 q = rgb.r < p.x ? vec4(p.xyw, rgb.r) : vec4(rgb.r, p.yzx);
+    float d = q.x - min(q.w, q.y);
+    float e = 1.0e-10;
+    return vec3(abs(q.z + (q.w - q.y) / (6.0 * d + e)), d / (q.x + e), q.x);
+}
+
+vec3 hsv_to_rgb(vec3 hsv) {
+    vec3 p = abs(fract(hsv.xxx + vec3(1.0, 2.0 / 3.0, 1.0 / 3.0)) * 6.0 - 3.0);
+    return hsv.z * mix(vec3(1.0), clamp(p - 1.0, 0.0, 1.0), hsv.y);
+}
+
+// Narkowicz's fit of the ACES filmic tone mapping curve, maps [0, inf) to [0, 1].
+vec3 tonemap_aces(vec3 rgb) {
+    vec3 x = rgb * 0.6;
+    return clamp((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14), 0.0, 1.0);
+}
+
+vec3 tonemap_reinhard(vec3 rgb) {
+    return rgb / (1.0 + luminance(rgb));
+}
@@ -0,0 +1,65 @@
+#pragma once
+
+// Integer hashes for procedural generation, after Jarzynski and Olano, "Hash Functions for GPU Rendering" (JCGT 2020).
+
+// PCG hash of one value.
+uint pcg_hash(uint v) {
+    uint state = v * 747796405u + 2891336453u;
+    uint word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+// PCG hash of two values, every output depends on every input.
+uvec2 pcg2d(uvec2 v) {
+    v = v * 1664525u + 1013904223u;
+    v.x += v.y * 1664525u;
+    v.y += v.x * 1664525u;
+    v = v ^ (v >> 16u);
+    v.x += v.y * 1664525u;
+    v.y += v.x * 1664525u;
+    v = v ^ (v >> 16u);
+    return v;
+}
+
+// PCG hash of three values, every output depends on every input.
+uvec3 pcg3d(uvec3 v) {
+    v = v * 1664525u + 1013904223u;
+    v.x += v.y * v.z;
+    v.y += v.z * v.x;
+    v.z += v.x * v.y;
+    v ^= v >> 16u;
+    v.x += v.y * v.z;
+    v.y += v.z * v.x;
+    v.z += v.x * v.y;
+    return v;
+}
+
+// Maps the upper 24 bits of a hash to a float in [0, 1).
+float hash_to_float(uint h) {
+    return float(h >> 8u) * (1.0 / 16777216.0);
+}
+
+vec2 hash_to_float(uvec2 h) {
+    return vec2(h >> 8u) * (1.0 / 16777216.0);
+}
+
+vec3 hash_to_float(uvec3 h) {
+    return vec3(h >> 8u) * (1.0 / 16777216.0);
+}
+
+// Hash of float coordinates, for example a cell position in noise.
+float hash12(vec2 p) {
+    return hash_to_float(pcg2d(floatBitsToUint(p)).x);
+}
+
+float hash13(vec3 p) {
+    return hash_to_float(pcg3d(floatBitsToUint(p)).x);
+}
+
+vec2 hash22(vec2 p) {
+    return hash_to_float(pcg2d(floatBitsToUint(p)));
+}
+
+vec3 hash33(vec3 p) {
+    return hash_to_float(pcg3d(floatBitsToUint(p)));
+}
@@ -0,0 +1,82 @@
+#pragma once
+
+// Value and gradient noise in [-1, 1] with fractal sums, built on the hashes in hash.glsl.
+
+#include <glsl_compiler/hash.glsl>
+
+// Quintic interpolation curve, its first and second derivative are 0 at 0 and 1.
+vec2 noise_fade(vec2 t) {
+    return t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+}
+
+vec3 noise_fade(vec3 t) {
+    return t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+}
+
+float value_noise(vec2 p) {
+    vec2 i = floor(p);
+    vec2 u = noise_fade(p - i);
+
+    float a = hash12(i);
+    float b = hash12(i + vec2(1.0, 0.0));
+    float c = hash12(i + vec2(0.0, 1.0));
+    float d = hash12(i + vec2(1.0, 1.0));
+    return mix(mix(a, b, u.x), mix(c, d, u.x), u.y) * 2.0 - 1.0;
+}
+
+float value_noise(vec3 p) {
+    vec3 i = floor(p);
+    vec3 u = noise_fade(p - i);
+
+    float a = mix(hash13(i), hash13(i + vec3(1.0, 0.0, 0.0)), u.x);
+    float b = mix(hash13(i + vec3(0.0, 1.0, 0.0)), hash13(i + vec3(1.0, 1.0, 0.0)), u.x);
+    float c = mix(hash13(i + vec3(0.0, 0.0, 1.0)), hash13(i + vec3(1.0, 0.0, 1.0)), u.x);
+    float d = mix(hash13(i + vec3(0.0, 1.0, 1.0)), hash13(i + vec3(1.0, 1.0, 1.0)), u.x);
+    return mix(mix(a, b, u.y), mix(c, d, u.y), u.z) * 2.0 - 1.0;
+}
+
+float noise_gradient_dot(vec2 cell, vec2 offset) {
+    vec2 gradient = normalize(hash22(cell) * 2.0 - 1.0 + 1e-6);
+    return dot(gradient, offset);
+}
+
+// Perlin style gradient noise.
+float gradient_noise(vec2 p) {
+    vec2 i = floor(p);
+    vec2 f = p - i;
+    vec2 u = noise_fade(f);
+
+    float a = noise_gradient_dot(i, f);
+    float b = noise_gradient_dot(i + vec2(1.0, 0.0), f - vec2(1.0, 0.0));
+    float c = noise_gradient_dot(i + vec2(0.0, 1.0), f - vec2(0.0, 1.0));
+    float d = noise_gradient_dot(i + vec2(1.0, 1.0), f - vec2(1.0, 1.0));
+    // The largest possible value of 2D gradient noise is sqrt(0.5).
+    return mix(mix(a, b, u.x), mix(c, d, u.x), u.y) * 1.41421356;
+}
+
+// Fractal sum of `octaves` layers of gradient noise, each with double the frequency and half the amplitude.
+float fbm(vec2 p, int octaves) {
+    float sum = 0.0;
+    float amplitude = 0.5;
+    float total = 0.0;
+    for (int i = 0; i < octaves; i++) {
+        sum += gradient_noise(p) * amplitude;
+        total += amplitude;
+        p = p * 2.0 + vec2(17.0, 31.0);
+        amplitude *= 0.5;
+    }
+    return total > 0.0 ? sum / total : 0.0;
+}
+
+float fbm(vec3 p, int octaves) {
+    float sum = 0.0;
+    float amplitude = 0.5;
+    float total = 0.0;
+    for (int i = 0; i < octaves; i++) {
+        sum += value_noise(p) * amplitude;
+        total += amplitude;
+        p = p * 2.0 + vec3(17.0, 31.0, 47.0);
+        amplitude *= 0.5;
+    }
+    return total > 0.0 ? sum / total : 0.0;
+}
@@ -0,0 +1,38 @@
+#pragma once
+
+// Compact encodings of unit vectors and colors for G-buffers and storage buffers.
+
+// Octahedral encoding of a unit vector into [-1, 1]^2.
+vec2 oct_encode(vec3 n) {
+    n /= abs(n.x) + abs(n.y) + abs(n.z);
+    vec2 wrapped = (1.0 - abs(n.yx)) * vec2(n.x >= 0.0 ? 1.0 : -1.0, n.y >= 0.0 ? 1.0 : -1.0);
+    return n.z >= 0.0 ? n.xy : wrapped;
+}
+
+vec3 oct_decode(vec2 e) {
+    vec3 n = vec3(e, 1.0 - abs(e.x) - abs(e.y));
+    float t = max(-n.z, 0.0);
+    n.x += n.x >= 0.0 ? -t : t;
+    n.y += n.y >= 0.0 ? -t : t;
+    return normalize(n);
+}
+
+// A unit vector in one uint with 16 bit per octahedral component.
+uint pack_normal(vec3 n) {
+    return packSnorm2x16(oct_encode(n));
+}
+
+vec3 unpack_normal(uint bits) {
+    return oct_decode(unpackSnorm2x16(bits));
+}
+
+// A color in [0, 1] with 10 bit per channel and a 2 bit alpha.
+uint pack_unorm_10_10_10_2(vec4 v) {
+    uvec4 u = uvec4(round(clamp(v, 0.0, 1.0) * vec4(1023.0, 1023.0, 1023.0, 3.0)));
+    return u.x | (u.y << 10u) | (u.z << 20u) | (u.w << 30u);
+}
+
+vec4 unpack_unorm_10_10_10_2(uint bits) {
+    uvec4 u = uvec4(bits, bits >> 10u, bits >> 20u, bits >> 30u) & uvec4(1023u, 1023u, 1023u, 3u);
+    return vec4(u) / vec4(1023.0, 1023.0, 1023.0, 3.0);
+}
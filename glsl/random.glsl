@@ -0,0 +1,45 @@
+#pragma once
+
+// A small random number generator with one uint of state, based on the PCG hash.
+
+#include <glsl_compiler/hash.glsl>
+
+// Seed for one invocation, different per pixel (or invocation id) and frame.
+uint rng_seed(uvec2 pixel, uint frame) {
+    return pcg_hash(pixel.x + pcg_hash(pixel.y + pcg_hash(frame)));
+}
+
+uint rng_next_uint(inout uint state) {
+    state = state * 747796405u + 2891336453u;
+    uint word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+// Uniform float in [0, 1).
+float rng_next_float(inout uint state) {
+    return hash_to_float(rng_next_uint(state));
+}
+
+vec2 rng_next_vec2(inout uint state) {
+    return vec2(rng_next_float(state), rng_next_float(state));
+}
+
+vec3 rng_next_vec3(inout uint state) {
+    return vec3(rng_next_float(state), rng_next_float(state), rng_next_float(state));
+}
+
+// Uniform direction on the unit sphere.
+vec3 random_unit_vector(inout uint state) {
+    vec2 u = rng_next_vec2(state);
+    float z = 1.0 - 2.0 * u.x;
+    float r = sqrt(max(0.0, 1.0 - z * z));
+    float phi = 6.28318530717958647692 * u.y;
+    return vec3(r * cos(phi), r * sin(phi), z);
+}
+
+// Cosine weighted direction in the hemisphere around the unit vector `normal`.
+vec3 random_cosine_hemisphere(vec3 normal, inout uint state) {
+    vec3 direction = normal + random_unit_vector(state);
+    // The sum is zero when the random vector points exactly opposite to the normal.
+    return dot(direction, direction) > 1e-8 ? normalize(direction) : normal;
+}
@@ -0,0 +1,149 @@
+use std::fmt::Write;
+use crate::profiler::ScopeResult;
+
+/// A scope of one capture placed on the timeline.
+struct Slice<'a> {
+    scope: &'a ScopeResult,
+    start: u64,
+    end: u64,
+    depth: usize,
+}
+
+/// Lays out the scopes of a capture that were hit like a flame graph. Every root scope starts at its last start time,
+/// its children follow each other from the start of their parent, each as long as its inclusive time.
+/// Scopes without parent that start while another one runs, like function scopes, go inside the deepest one.
+fn nested_slices(scopes: &[ScopeResult]) -> Vec<Slice<'_>> {
+    let mut roots: Vec<usize> = (0..scopes.len()).filter(|i| scopes[*i].parent.is_none()).collect();
+    roots.sort_by_key(|i| scopes[*i].start_ticks);
+
+    let mut slices: Vec<Slice> = vec![];
+    for root in roots {
+        let start = scopes[root].start_ticks;
+        let container = slices.iter()
+            .filter(|slice| slice.start <= start && start < slice.end)
+            .max_by_key(|slice| slice.depth);
+        let (limit, depth) = container.map_or((u64::MAX, 0), |slice| (slice.end, slice.depth + 1));
+        place_scope(scopes, root, start, limit, depth, &mut slices);
+    }
+    slices.sort_by_key(|slice| (slice.start, slice.depth));
+    slices
+}
+
+/// Places the scope `id` and its children at `start`, ending at `limit` at the latest. Returns the end of the scope.
+fn place_scope<'a>(scopes: &'a [ScopeResult], id: usize, start: u64, limit: u64, depth: usize, slices: &mut Vec<Slice<'a>>) -> u64 {
+    let scope = &scopes[id];
+    if scope.hit_count == 0 {
+        return start
+    }
+
+    let end = start.saturating_add(scope.inclusive_ticks).min(limit);
+    slices.push(Slice { scope, start, end, depth });
+
+    let mut child_start = start;
+    for child in (0..scopes.len()).filter(|i| scopes[*i].parent == Some(id)) {
+        child_start = place_scope(scopes, child, child_start, end, depth + 1, slices);
+    }
+    end
+}
+
+/// The earliest start of all captures, timestamps of the exports are relative to it.
+fn origin(captures: &[Vec<ScopeResult>]) -> u64 {
+    captures.iter()
+        .flatten()
+        .filter(|scope| scope.hit_count > 0 && scope.parent.is_none())
+        .map(|scope| scope.start_ticks)
+        .min()
+        .unwrap_or(0)
+}
+
+fn microseconds(ticks: u64, ticks_per_microsecond: f64) -> f64 {
+    ticks as f64 / ticks_per_microsecond
+}
+
+/// Converts decoded captures into Chrome Trace Event JSON, viewable in `chrome://tracing` or Perfetto.
+/// Every capture is one frame, `ticks_per_microsecond` is the frequency of the shader's realtime clock.
+pub fn chrome_trace(captures: &[Vec<ScopeResult>], ticks_per_microsecond: f64) -> String {
+    let origin = origin(captures);
+
+    let mut events = vec![];
+    for (frame, scopes) in captures.iter().enumerate() {
+        for slice in nested_slices(scopes) {
+            let mut event = String::new();
+            write!(event, r#"{{"name":{},"cat":"shader","ph":"X","ts":{},"dur":{},"pid":0,"tid":0,"args":{{"frame":{frame},"hits":{},"self_ticks":{}}}}}"#,
+                json_string(&slice.scope.name),
+                microseconds(slice.start.wrapping_sub(origin), ticks_per_microsecond),
+                microseconds(slice.end - slice.start, ticks_per_microsecond),
+                slice.scope.hit_count,
+                slice.scope.self_ticks,
+            ).unwrap();
+            events.push(event);
+        }
+    }
+
+    format!(r#"{{"traceEvents":[{}],"displayTimeUnit":"ns"}}"#, events.join(","))
+}
+
+/// Converts decoded captures into the speedscope file format, with one evented profile per capture.
+/// `ticks_per_microsecond` is the frequency of the shader's realtime clock.
+pub fn speedscope(captures: &[Vec<ScopeResult>], ticks_per_microsecond: f64) -> String {
+    let origin = origin(captures);
+
+    let mut frame_names: Vec<&str> = vec![];
+    let mut profiles = vec![];
+    for (frame, scopes) in captures.iter().enumerate() {
+        let slices = nested_slices(scopes);
+
+        // Open and close events in order. A scope closes before the next one at its depth or above opens.
+        let mut events = vec![];
+        let mut open: Vec<(usize, u64)> = vec![];
+        let close = |events: &mut Vec<String>, (index, end): (usize, u64)| {
+            events.push(format!(r#"{{"type":"C","frame":{index},"at":{}}}"#, microseconds(end.wrapping_sub(origin), ticks_per_microsecond)));
+        };
+        for slice in &slices {
+            while open.len() > slice.depth {
+                close(&mut events, open.pop().unwrap());
+            }
+
+            let index = frame_names.iter().position(|name| *name == slice.scope.name).unwrap_or_else(|| {
+                frame_names.push(&slice.scope.name);
+                frame_names.len() - 1
+            });
+            events.push(format!(r#"{{"type":"O","frame":{index},"at":{}}}"#, microseconds(slice.start.wrapping_sub(origin), ticks_per_microsecond)));
+            open.push((index, slice.end));
+        }
+        while let Some(entry) = open.pop() {
+            close(&mut events, entry);
+        }
+
+        let start = slices.iter().map(|slice| slice.start.wrapping_sub(origin)).min().unwrap_or(0);
+        let end = slices.iter().map(|slice| slice.end.wrapping_sub(origin)).max().unwrap_or(0);
+        profiles.push(format!(r#"{{"type":"evented","name":"frame {frame}","unit":"microseconds","startValue":{},"endValue":{},"events":[{}]}}"#,
+            microseconds(start, ticks_per_microsecond),
+            microseconds(end, ticks_per_microsecond),
+            events.join(","),
+        ));
+    }
+
+    let frames: Vec<String> = frame_names.iter().map(|name| format!(r#"{{"name":{}}}"#, json_string(name))).collect();
+    format!(r#"{{"$schema":"https://www.speedscope.app/file-format-schema.json","shared":{{"frames":[{}]}},"profiles":[{}],"name":"glsl_compiler profile","activeProfileIndex":0,"exporter":"glsl_compiler_runtime"}}"#,
+        frames.join(","),
+        profiles.join(","),
+    )
+}
+
+fn json_string(text: &str) -> String {
+    let mut escaped = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(escaped, "\\u{:04x}", c as u32).unwrap(),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
@@ -0,0 +1,48 @@
+/// What a `profile` marco returns next to the binary: the slots of `ProfilerOut` and where the profiler buffers are bound.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProfileInfo {
+    /// The name of every slot of `ProfilerOut`, scopes and counters, by id.
+    pub names: &'static [&'static str],
+    /// The enclosing scope of every slot. `None` for `main`, counters and scopes outside of any other scope.
+    pub parents: &'static [Option<usize>],
+    /// The slots that are `COUNT` counters.
+    pub counters: &'static [usize],
+    /// Whether the shader was compiled with `profile = aggregate`.
+    pub aggregate: bool,
+    pub layout: ProfileLayout,
+    /// The printf format strings with `profile = printf`.
+    pub printf: Option<PrintfInfo>,
+}
+
+/// Where the injected buffers are bound.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProfileLayout {
+    pub set: u32,
+    /// Binding of the `ProfilerIn` uniform buffer. `None` with `push_constant` or `profile = aggregate`.
+    pub in_binding: Option<u32>,
+    /// Whether `ProfilerIn` is a push constant block.
+    pub push_constant: bool,
+    pub out_binding: u32,
+}
+
+/// The `ProfilerPrintf` ring buffer of `profile = printf`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PrintfInfo {
+    pub binding: u32,
+    /// `uint`s per record: the format id followed by the values.
+    pub record: usize,
+    /// The format strings by id, as written in the shader.
+    pub formats: &'static [&'static str],
+}
+
+impl ProfileInfo {
+    /// Whether the slot `id` is a counter.
+    pub fn is_counter(&self, id: usize) -> bool {
+        self.counters.contains(&id)
+    }
+
+    /// The enclosing scope of the slot `id`.
+    pub fn parent(&self, id: usize) -> Option<usize> {
+        self.parents.get(id).copied().flatten()
+    }
+}
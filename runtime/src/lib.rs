@@ -0,0 +1,52 @@
+/*!
+
+Host side helpers for shaders compiled with the `glsl_compiler` marco.
+
+## Decoding profiler output
+A `profile` marco returns a `ProfileInfo` with the scope names and bindings, the profiled shader writes the measurements into its `ProfilerOut` buffer.
+`decode` turns both into one result per scope with its parent, inclusive and self time:
+```Rust
+let (bin, profile_info): (&[u8], &ProfileInfo) = glsl!{type = Compute, profile, code = { ... }};
+...
+let scopes = glsl_compiler_runtime::decode_bytes(profile_info, mapped_profiler_out)?;
+for scope in scopes {
+    println!("{}: {} hits, {} ticks, {} in the scope itself", scope.name, scope.hit_count, scope.inclusive_ticks, scope.self_ticks);
+}
+```
+
+With `profile = aggregate` use `decode_aggregate`, which returns the count, total, self, min and max ticks of every scope over all invocations.
+Clear the buffer to 0 before every dispatch, e.g. with `vkCmdFillBuffer`.
+
+`decode_counters` returns the `COUNT` counters of the shader in both modes, with how often each was reached and the sum of its values.
+
+## printf
+With `profile = printf` the shader writes its `printf` lines as records into the `ProfilerPrintf` buffer, a `uint head` followed by a ring of records.
+`decode_printf` formats them with the format strings from `ProfileInfo::printf`, oldest first. Clear `head` to 0 before every dispatch.
+```Rust
+for line in glsl_compiler_runtime::decode_printf_bytes(profile_info, mapped_profiler_printf)? {
+    println!("{line}");
+}
+```
+
+## Viewing captures
+`chrome_trace` and `speedscope` convert one or more captures, e.g. one per frame, into JSON for
+[Perfetto](https://ui.perfetto.dev) / `chrome://tracing` and [speedscope](https://www.speedscope.app).
+Child scopes are drawn inside their parent one after another, as long as their inclusive time. The second argument is the frequency of the realtime clock in ticks per microsecond.
+```Rust
+let captures: Vec<Vec<ScopeResult>> = frames.iter().map(|data| decode(profile_info, data)).collect::<Result<_, _>>()?;
+std::fs::write("profile.json", glsl_compiler_runtime::chrome_trace(&captures, 1000.0))?;
+```
+*/
+
+mod profiler;
+mod info;
+mod export;
+mod printf;
+
+pub use export::{chrome_trace, speedscope};
+pub use info::{PrintfInfo, ProfileInfo, ProfileLayout};
+pub use printf::{decode_printf, decode_printf_bytes};
+pub use profiler::{
+    decode, decode_aggregate, decode_aggregate_bytes, decode_bytes, decode_counters, decode_counters_bytes,
+    AggregateResult, CounterResult, DecodeError, ScopeResult, WORDS_PER_SCOPE,
+};
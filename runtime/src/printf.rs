@@ -0,0 +1,267 @@
+use std::fmt::Write;
+use crate::info::ProfileInfo;
+use crate::profiler::DecodeError;
+
+/// A conversion like `%-8.3v2f`, parsed from a printf format string.
+#[derive(Default)]
+struct Conversion {
+    left: bool,
+    plus: bool,
+    space: bool,
+    zero: bool,
+    alternate: bool,
+    width: usize,
+    precision: Option<usize>,
+    components: usize,
+    conversion: char,
+}
+
+/// A piece of a format string: literal text or a conversion.
+enum Piece {
+    Text(String),
+    Conversion(Conversion),
+}
+
+/// Formats the `ProfilerPrintf` buffer of a shader compiled with `profile = printf` into lines, oldest first.
+/// `data` starts with `head`, which has to be cleared to 0 before the dispatch. When more lines were printed
+/// than the buffer holds, only the most recent ones are returned.
+pub fn decode_printf(info: &ProfileInfo, data: &[u32]) -> Result<Vec<String>, DecodeError> {
+    if info.printf.is_none() {
+        return Err(DecodeError::PrintfDisabled)
+    }
+    let printf = info.printf.unwrap();
+    let record = printf.record.max(1);
+    if data.len() < 1 + record {
+        return Err(DecodeError::BufferTooSmall { expected: 1 + record, found: data.len() })
+    }
+
+    let formats: Vec<Vec<Piece>> = printf.formats.iter().map(|format| parse_format(&unescape(format))).collect();
+
+    let head = data[0] as usize;
+    let records: Vec<&[u32]> = data[1..].chunks_exact(record).collect();
+    let mut lines = vec![];
+    for index in head.saturating_sub(records.len())..head {
+        let words = records[index % records.len()];
+        let format = formats.get(words[0] as usize);
+        if format.is_none() {
+            return Err(DecodeError::UnknownFormat { id: words[0] })
+        }
+        lines.push(format_record(format.unwrap(), &words[1..]));
+    }
+    Ok(lines)
+}
+
+/// Resolves the escape sequences of a GLSL string literal.
+fn unescape(format: &str) -> String {
+    let mut text = String::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            text.push(c);
+            continue
+        }
+        match chars.next() {
+            Some('n') => text.push('\n'),
+            Some('t') => text.push('\t'),
+            Some('r') => text.push('\r'),
+            Some(c) => text.push(c),
+            None => text.push('\\'),
+        }
+    }
+    text
+}
+
+fn parse_format(format: &str) -> Vec<Piece> {
+    let mut pieces = vec![];
+    let mut text = String::new();
+    let mut chars = format.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            text.push(c);
+            continue
+        }
+        if chars.next_if_eq(&'%').is_some() {
+            text.push('%');
+            continue
+        }
+
+        let mut conversion = Conversion { components: 1, ..Default::default() };
+        while let Some(flag) = chars.next_if(|c| "-+ 0#".contains(*c)) {
+            match flag {
+                '-' => conversion.left = true,
+                '+' => conversion.plus = true,
+                ' ' => conversion.space = true,
+                '0' => conversion.zero = true,
+                _ => conversion.alternate = true,
+            }
+        }
+        while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+            conversion.width = conversion.width * 10 + digit.to_digit(10).unwrap() as usize;
+        }
+        if chars.next_if_eq(&'.').is_some() {
+            let mut precision = 0;
+            while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+                precision = precision * 10 + digit.to_digit(10).unwrap() as usize;
+            }
+            conversion.precision = Some(precision);
+        }
+        if chars.next_if_eq(&'v').is_some() {
+            conversion.components = chars.next().and_then(|size| size.to_digit(10)).unwrap_or(1) as usize;
+        }
+        conversion.conversion = chars.next().unwrap_or('%');
+
+        pieces.push(Piece::Text(std::mem::take(&mut text)));
+        pieces.push(Piece::Conversion(conversion));
+    }
+    pieces.push(Piece::Text(text));
+    pieces
+}
+
+/// Formats the values of one record. Missing values print as 0.
+fn format_record(pieces: &[Piece], values: &[u32]) -> String {
+    let mut line = String::new();
+    let mut values = values.iter().copied();
+    for piece in pieces {
+        match piece {
+            Piece::Text(text) => line.push_str(text),
+            Piece::Conversion(conversion) => {
+                let components: Vec<String> = (0..conversion.components)
+                    .map(|_| format_value(conversion, values.next().unwrap_or(0)))
+                    .collect();
+                line.push_str(&components.join(", "));
+            }
+        }
+    }
+    line
+}
+
+/// Formats one value like C's printf does.
+fn format_value(conversion: &Conversion, bits: u32) -> String {
+    let signed = matches!(conversion.conversion, 'd' | 'i' | 'f' | 'F' | 'e' | 'E' | 'g' | 'G');
+    let upper = conversion.conversion.is_ascii_uppercase();
+
+    let (negative, mut digits) = match conversion.conversion {
+        'd' | 'i' => ((bits as i32) < 0, integer_digits((bits as i32).unsigned_abs().to_string(), conversion.precision)),
+        'u' => (false, integer_digits(bits.to_string(), conversion.precision)),
+        'x' | 'X' => {
+            let digits = integer_digits(if upper { format!("{bits:X}") } else { format!("{bits:x}") }, conversion.precision);
+            let prefix = if conversion.alternate && bits != 0 { if upper { "0X" } else { "0x" } } else { "" };
+            (false, format!("{prefix}{digits}"))
+        }
+        'o' => {
+            let digits = integer_digits(format!("{bits:o}"), conversion.precision);
+            (false, if conversion.alternate && !digits.starts_with('0') { format!("0{digits}") } else { digits })
+        }
+        'c' => (false, char::from_u32(bits).unwrap_or(char::REPLACEMENT_CHARACTER).to_string()),
+        _ => {
+            let value = f32::from_bits(bits) as f64;
+            (value.is_sign_negative() && !value.is_nan(), float_digits(conversion, value.abs()))
+        }
+    };
+    if upper {
+        digits = digits.to_uppercase();
+    }
+
+    let sign = if negative {
+        "-"
+    } else if signed && conversion.plus {
+        "+"
+    } else if signed && conversion.space {
+        " "
+    } else {
+        ""
+    };
+
+    let length = sign.len() + digits.chars().count();
+    let padding = conversion.width.saturating_sub(length);
+    let finite = digits.starts_with(|c: char| c.is_ascii_digit());
+    let integer = matches!(conversion.conversion, 'd' | 'i' | 'u' | 'x' | 'X' | 'o');
+    if conversion.left {
+        format!("{sign}{digits}{}", " ".repeat(padding))
+    } else if conversion.zero && finite && !(integer && conversion.precision.is_some()) {
+        let (prefix, digits) = if digits.starts_with("0x") || digits.starts_with("0X") { digits.split_at(2) } else { ("", digits.as_str()) };
+        format!("{sign}{prefix}{}{digits}", "0".repeat(padding))
+    } else {
+        format!("{}{sign}{digits}", " ".repeat(padding))
+    }
+}
+
+/// Pads integer digits with zeros to the precision, which is the minimum number of digits.
+fn integer_digits(digits: String, precision: Option<usize>) -> String {
+    match precision {
+        Some(0) if digits == "0" => String::new(),
+        Some(precision) if digits.len() < precision => format!("{}{digits}", "0".repeat(precision - digits.len())),
+        _ => digits,
+    }
+}
+
+/// The digits of a non-negative float for `%f`, `%e` and `%g`, in lower case.
+fn float_digits(conversion: &Conversion, value: f64) -> String {
+    if value.is_nan() {
+        return "nan".to_string()
+    }
+    if value.is_infinite() {
+        return "inf".to_string()
+    }
+
+    let precision = conversion.precision.unwrap_or(6);
+    match conversion.conversion {
+        'f' | 'F' => fixed(value, precision, conversion.alternate),
+        'e' | 'E' => exponential(value, precision, conversion.alternate),
+        _ => {
+            // %g uses %e for very small or large exponents and drops trailing zeros.
+            let precision = precision.max(1);
+            let exponent = exponential(value, precision - 1, false)
+                .rsplit_once('e')
+                .and_then(|(_, exponent)| exponent.parse::<i32>().ok())
+                .unwrap_or(0);
+            let digits = if exponent < -4 || exponent >= precision as i32 {
+                exponential(value, precision - 1, conversion.alternate)
+            } else {
+                fixed(value, (precision as i32 - 1 - exponent) as usize, conversion.alternate)
+            };
+            if conversion.alternate {
+                return digits
+            }
+            match digits.split_once('e') {
+                Some((mantissa, exponent)) => format!("{}e{exponent}", trim_fraction(mantissa)),
+                None => trim_fraction(&digits).to_string(),
+            }
+        }
+    }
+}
+
+fn fixed(value: f64, precision: usize, alternate: bool) -> String {
+    let mut digits = format!("{value:.precision$}");
+    if alternate && precision == 0 {
+        digits.push('.');
+    }
+    digits
+}
+
+/// `%e` formatting with at least two exponent digits, like `1.500000e+01`.
+fn exponential(value: f64, precision: usize, alternate: bool) -> String {
+    let digits = format!("{value:.precision$e}");
+    let (mantissa, exponent) = digits.split_once('e').unwrap();
+    let exponent: i32 = exponent.parse().unwrap();
+
+    let mut text = mantissa.to_string();
+    if alternate && precision == 0 {
+        text.push('.');
+    }
+    write!(text, "e{}{:02}", if exponent < 0 { '-' } else { '+' }, exponent.unsigned_abs()).unwrap();
+    text
+}
+
+fn trim_fraction(digits: &str) -> &str {
+    if digits.contains('.') {
+        digits.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        digits
+    }
+}
+
+/// Like `decode_printf`, for a mapped buffer in native byte order.
+pub fn decode_printf_bytes(info: &ProfileInfo, bytes: &[u8]) -> Result<Vec<String>, DecodeError> {
+    decode_printf(info, &crate::profiler::words(bytes)?)
+}
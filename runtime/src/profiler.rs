@@ -0,0 +1,231 @@
+use std::fmt;
+use crate::info::ProfileInfo;
+
+/// `uint`s the profiler writes per scope. For a single invocation: hit count, total ticks low/high, last start low/high.
+/// With `profile = aggregate`: count, total ticks low/high, `!min` and max.
+/// Counters use the same number of `uint`s: hit count and the sum of the values as low/high word.
+pub const WORDS_PER_SCOPE: usize = 5;
+
+/// The measurement of one profile scope.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScopeResult {
+    pub name: String,
+    /// How often the scope was entered. 0 if it never ran.
+    pub hit_count: u32,
+    /// Index of the enclosing scope. `None` for `main` and scopes outside of any other scope, like the ones of functions.
+    pub parent: Option<usize>,
+    /// Clock value of the last start of the scope.
+    pub start_ticks: u64,
+    /// Clock ticks spent in the scope over all hits, including its child scopes.
+    pub inclusive_ticks: u64,
+    /// Clock ticks spent in the scope itself, without its child scopes.
+    pub self_ticks: u64,
+}
+
+/// The measurements of one profile scope over all invocations of a dispatch, from `profile = aggregate`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AggregateResult {
+    pub name: String,
+    /// How often the scope was run by all invocations together.
+    pub count: u32,
+    /// Index of the enclosing scope. `None` for `main` and scopes outside of any other scope, like the ones of functions.
+    pub parent: Option<usize>,
+    /// Clock ticks of all runs, including child scopes.
+    pub total_ticks: u64,
+    /// Clock ticks of all runs without child scopes.
+    pub self_ticks: u64,
+    /// The shortest and longest run in clock ticks. 0 if the scope never ran.
+    pub min_ticks: u32,
+    pub max_ticks: u32,
+}
+
+/// The value of a `COUNT("<name>", <value>)` counter.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CounterResult {
+    pub name: String,
+    /// How often the counter was reached.
+    pub hits: u32,
+    /// The sum of all counted values. Equal to `hits` for `//COUNT("<name>")`.
+    pub value: u64,
+}
+
+impl AggregateResult {
+    /// Average clock ticks of one run.
+    pub fn mean_ticks(&self) -> f64 {
+        if self.count == 0 {
+            return 0.0
+        }
+        self.total_ticks as f64 / self.count as f64
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The buffer holds fewer `uint`s than the scopes need.
+    BufferTooSmall { expected: usize, found: usize },
+    /// The length of a byte buffer isn't a multiple of 4.
+    UnalignedBuffer { len: usize },
+    /// `decode` got the info of an aggregate profile or `decode_aggregate` the one of a single invocation profile.
+    ModeMismatch { aggregate: bool },
+    /// `decode_printf` got the info of a shader compiled without `profile = printf`.
+    PrintfDisabled,
+    /// A printf record refers to a format string the info doesn't contain.
+    UnknownFormat { id: u32 },
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::BufferTooSmall { expected, found } => {
+                write!(f, "profiler buffer holds {found} uints, but the scopes need {expected}")
+            }
+            DecodeError::UnalignedBuffer { len } => write!(f, "profiler buffer length {len} is not a multiple of 4"),
+            DecodeError::ModeMismatch { aggregate: true } => write!(f, "the shader was profiled with profile = aggregate, use decode_aggregate"),
+            DecodeError::ModeMismatch { aggregate: false } => write!(f, "the shader wasn't profiled with profile = aggregate, use decode"),
+            DecodeError::PrintfDisabled => write!(f, "the shader wasn't compiled with profile = printf"),
+            DecodeError::UnknownFormat { id } => write!(f, "printf record refers to unknown format {id}"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Decodes the `ProfilerOut` data of a profiled shader. `info` is the `ProfileInfo` returned by the marco.
+pub fn decode(info: &ProfileInfo, data: &[u32]) -> Result<Vec<ScopeResult>, DecodeError> {
+    if info.aggregate {
+        return Err(DecodeError::ModeMismatch { aggregate: true })
+    }
+
+    let scopes = scopes(info, data)?;
+    let inclusive_ticks: Vec<u64> = scopes.iter().map(|(_, words, _)| combine(words[1], words[2])).collect();
+    let self_ticks = self_ticks(&scopes, &inclusive_ticks);
+
+    let results = scopes.iter()
+        .zip(inclusive_ticks)
+        .zip(self_ticks)
+        .map(|(((name, words, parent), inclusive_ticks), self_ticks)| ScopeResult {
+            name: name.to_string(),
+            hit_count: words[0],
+            parent: *parent,
+            start_ticks: combine(words[3], words[4]),
+            inclusive_ticks,
+            self_ticks,
+        })
+        .collect();
+    Ok(results)
+}
+
+/// Decodes the `ProfilerOut` data of a shader profiled with `profile = aggregate`.
+/// The buffer has to be cleared to 0 before the dispatch.
+pub fn decode_aggregate(info: &ProfileInfo, data: &[u32]) -> Result<Vec<AggregateResult>, DecodeError> {
+    if !info.aggregate {
+        return Err(DecodeError::ModeMismatch { aggregate: false })
+    }
+
+    let scopes = scopes(info, data)?;
+    let total_ticks: Vec<u64> = scopes.iter().map(|(_, words, _)| combine(words[1], words[2])).collect();
+    let self_ticks = self_ticks(&scopes, &total_ticks);
+
+    let results = scopes.iter()
+        .zip(total_ticks)
+        .zip(self_ticks)
+        .map(|(((name, words, parent), total_ticks), self_ticks)| {
+            let count = words[0];
+            AggregateResult {
+                name: name.to_string(),
+                count,
+                parent: *parent,
+                total_ticks,
+                self_ticks,
+                // The shader stores the minimum inverted, so it can use atomicMax on a cleared buffer as well.
+                min_ticks: if count == 0 { 0 } else { !words[3] },
+                max_ticks: words[4],
+            }
+        })
+        .collect();
+    Ok(results)
+}
+
+/// Decodes the counters of a profiled shader, with or without `profile = aggregate`.
+pub fn decode_counters(info: &ProfileInfo, data: &[u32]) -> Result<Vec<CounterResult>, DecodeError> {
+    let results = slots(info, data)?.into_iter()
+        .enumerate()
+        .filter(|(id, _)| info.is_counter(*id))
+        .map(|(_, (name, words))| CounterResult {
+            name: name.to_string(),
+            hits: words[0],
+            value: combine(words[1], words[2]),
+        })
+        .collect();
+    Ok(results)
+}
+
+/// The name, the words in the buffer and the parent of a scope.
+type ScopeWords<'a> = (&'static str, &'a [u32], Option<usize>);
+
+/// The name and the words in `data` of every scope and counter.
+fn slots<'a>(info: &ProfileInfo, data: &'a [u32]) -> Result<Vec<(&'static str, &'a [u32])>, DecodeError> {
+    let expected = info.names.len() * WORDS_PER_SCOPE;
+    if data.len() < expected {
+        return Err(DecodeError::BufferTooSmall { expected, found: data.len() })
+    }
+    Ok(info.names.iter().copied().zip(data.chunks_exact(WORDS_PER_SCOPE)).collect())
+}
+
+/// The scopes without the counters. Parents are indices into the returned scopes.
+fn scopes<'a>(info: &ProfileInfo, data: &'a [u32]) -> Result<Vec<ScopeWords<'a>>, DecodeError> {
+    let slots = slots(info, data)?;
+    let num_slots = slots.len();
+
+    let scope_index = |slot: usize| (0..slot).filter(|id| !info.is_counter(*id)).count();
+    Ok(slots.into_iter()
+        .enumerate()
+        .filter(|(id, _)| !info.is_counter(*id))
+        .map(|(id, (name, words))| {
+            let parent = info.parent(id).filter(|parent| *parent < num_slots && *parent != id && !info.is_counter(*parent));
+            (name, words, parent.map(scope_index))
+        })
+        .collect())
+}
+
+/// The ticks of every scope minus the ticks of its children. Scopes without parent aren't subtracted anywhere,
+/// a function scope is part of whichever scope called it.
+fn self_ticks(scopes: &[ScopeWords], inclusive_ticks: &[u64]) -> Vec<u64> {
+    let mut self_ticks = inclusive_ticks.to_vec();
+    for ((_, _, parent), ticks) in scopes.iter().zip(inclusive_ticks) {
+        if let Some(parent) = parent {
+            self_ticks[*parent] = self_ticks[*parent].saturating_sub(*ticks);
+        }
+    }
+    self_ticks
+}
+
+/// Like `decode`, for a mapped buffer in native byte order.
+pub fn decode_bytes(info: &ProfileInfo, bytes: &[u8]) -> Result<Vec<ScopeResult>, DecodeError> {
+    decode(info, &words(bytes)?)
+}
+
+/// Like `decode_counters`, for a mapped buffer in native byte order.
+pub fn decode_counters_bytes(info: &ProfileInfo, bytes: &[u8]) -> Result<Vec<CounterResult>, DecodeError> {
+    decode_counters(info, &words(bytes)?)
+}
+
+/// Like `decode_aggregate`, for a mapped buffer in native byte order.
+pub fn decode_aggregate_bytes(info: &ProfileInfo, bytes: &[u8]) -> Result<Vec<AggregateResult>, DecodeError> {
+    decode_aggregate(info, &words(bytes)?)
+}
+
+pub(crate) fn words(bytes: &[u8]) -> Result<Vec<u32>, DecodeError> {
+    if !bytes.len().is_multiple_of(4) {
+        return Err(DecodeError::UnalignedBuffer { len: bytes.len() })
+    }
+
+    Ok(bytes.chunks_exact(4)
+        .map(|word| u32::from_ne_bytes([word[0], word[1], word[2], word[3]]))
+        .collect())
+}
+
+/// A 64-bit value the shader wrote as low and high word, like `clockRealtime2x32EXT()` returns it.
+fn combine(low: u32, high: u32) -> u64 {
+    (high as u64) << 32 | low as u64
+}
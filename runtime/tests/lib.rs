@@ -0,0 +1,278 @@
+use glsl_compiler_runtime::{
+    chrome_trace, decode, decode_aggregate, decode_aggregate_bytes, decode_bytes, decode_counters, decode_counters_bytes, decode_printf,
+    decode_printf_bytes, speedscope,
+    AggregateResult, CounterResult, DecodeError, PrintfInfo, ProfileInfo, ProfileLayout, ScopeResult,
+};
+
+const LAYOUT: ProfileLayout = ProfileLayout { set: 0, in_binding: Some(10), push_constant: false, out_binding: 11 };
+
+fn info(names: &'static [&'static str], parents: &'static [Option<usize>], counters: &'static [usize]) -> ProfileInfo {
+    ProfileInfo { names, parents, counters, aggregate: false, layout: LAYOUT, printf: None }
+}
+
+fn aggregate_info(names: &'static [&'static str], parents: &'static [Option<usize>], counters: &'static [usize]) -> ProfileInfo {
+    ProfileInfo { aggregate: true, layout: ProfileLayout { in_binding: None, ..LAYOUT }, ..info(names, parents, counters) }
+}
+
+fn printf_info(record: usize, formats: &'static [&'static str]) -> ProfileInfo {
+    ProfileInfo { printf: Some(PrintfInfo { binding: 12, record, formats }), ..info(&[], &[], &[]) }
+}
+
+fn scope(name: &str, hit_count: u32, parent: Option<usize>, start_ticks: u64, inclusive_ticks: u64, self_ticks: u64) -> ScopeResult {
+    ScopeResult { name: name.to_string(), hit_count, parent, start_ticks, inclusive_ticks, self_ticks }
+}
+
+#[test]
+fn decode_scopes() {
+    let info = info(&["main", "loop"], &[None, Some(0)], &[]);
+    let data = [
+        1, 350, 0, 100, 0,
+        8, 200, 0, 220, 0,
+    ];
+
+    let scopes = decode(&info, &data).unwrap();
+    assert_eq!(scopes, vec![scope("main", 1, None, 100, 350, 150), scope("loop", 8, Some(0), 220, 200, 200)]);
+}
+
+#[test]
+fn decode_self_time() {
+    let info = info(&["shade", "main", "loop"], &[Some(2), None, Some(1)], &[]);
+    let data = [
+        4, 40, 0, 0, 0,
+        1, 100, 0, 0, 0,
+        4, 60, 0, 0, 0,
+    ];
+
+    let scopes = decode(&info, &data).unwrap();
+    let self_ticks: Vec<u64> = scopes.iter().map(|scope| scope.self_ticks).collect();
+    assert_eq!(self_ticks, vec![40, 40, 20]);
+}
+
+#[test]
+fn decode_function_scope_in_nested_scope() {
+    // shade is a function scope called inside loop, its time is part of loop but not subtracted again from main.
+    let info = info(&["shade", "main", "loop"], &[None, None, Some(1)], &[]);
+    let data = [
+        4, 40, 0, 1250, 0,
+        1, 100, 0, 1000, 0,
+        4, 60, 0, 1100, 0,
+    ];
+
+    let scopes = decode(&info, &data).unwrap();
+    assert_eq!(scopes, vec![
+        scope("shade", 4, None, 1250, 40, 40),
+        scope("main", 1, None, 1000, 100, 40),
+        scope("loop", 4, Some(1), 1100, 60, 60),
+    ]);
+}
+
+#[test]
+fn decode_high_word() {
+    let info = info(&["main"], &[None], &[]);
+    let data = [1, 0xFFFF_FFF0, 7, 0x10, 8];
+
+    let scopes = decode(&info, &data).unwrap();
+    assert_eq!(scopes, vec![scope("main", 1, None, 0x8_0000_0010, 0x7_FFFF_FFF0, 0x7_FFFF_FFF0)]);
+}
+
+#[test]
+fn decode_scope_not_hit() {
+    let info = info(&["main", "branch"], &[None, Some(0)], &[]);
+    let data = [1, 10, 0, 20, 0, 0, 0, 0, 0, 0];
+
+    let scopes = decode(&info, &data).unwrap();
+    assert_eq!(scopes[1], scope("branch", 0, Some(0), 0, 0, 0));
+}
+
+#[test]
+fn decode_bytes_native_endian() {
+    let info = info(&["main"], &[None], &[]);
+    let bytes: Vec<u8> = [1u32, 4, 0, 5, 0].iter().flat_map(|word| word.to_ne_bytes()).collect();
+
+    let scopes = decode_bytes(&info, &bytes).unwrap();
+    assert_eq!(scopes, vec![scope("main", 1, None, 5, 4, 4)]);
+}
+
+#[test]
+fn decode_buffer_too_small() {
+    let info = info(&["main", "loop"], &[None, Some(0)], &[]);
+    let data = [1, 10, 0, 20, 0];
+
+    assert_eq!(decode(&info, &data), Err(DecodeError::BufferTooSmall { expected: 10, found: 5 }));
+    assert_eq!(decode_bytes(&info, &[0; 6]), Err(DecodeError::UnalignedBuffer { len: 6 }));
+}
+
+#[test]
+fn decode_aggregate_scopes() {
+    let info = aggregate_info(&["main", "even", "never"], &[None, Some(0), Some(0)], &[]);
+    let data = [
+        64, 0xFFFF_FF00, 1, !100, 0x300,
+        32, 3200, 0, !90, 110,
+        0, 0, 0, 0, 0,
+    ];
+
+    let scopes = decode_aggregate(&info, &data).unwrap();
+    assert_eq!(scopes[0], AggregateResult {
+        name: "main".to_string(),
+        count: 64,
+        parent: None,
+        total_ticks: 0x1_FFFF_FF00,
+        self_ticks: 0x1_FFFF_FF00 - 3200,
+        min_ticks: 100,
+        max_ticks: 0x300,
+    });
+    assert_eq!((scopes[1].min_ticks, scopes[1].max_ticks, scopes[1].mean_ticks()), (90, 110, 100.0));
+    assert_eq!((scopes[2].count, scopes[2].min_ticks, scopes[2].mean_ticks()), (0, 0, 0.0));
+}
+
+#[test]
+fn decode_mode_mismatch() {
+    let aggregate_info = aggregate_info(&["main"], &[None], &[]);
+    let single_info = info(&["main"], &[None], &[]);
+    let data = [0; 5];
+
+    assert_eq!(decode(&aggregate_info, &data), Err(DecodeError::ModeMismatch { aggregate: true }));
+    assert_eq!(decode_aggregate(&single_info, &data), Err(DecodeError::ModeMismatch { aggregate: false }));
+    assert_eq!(decode_aggregate_bytes(&aggregate_info, &[0; 20]).unwrap()[0].count, 0);
+}
+
+#[test]
+fn decode_scopes_and_counters() {
+    let info = info(&["main", "iterations", "loop", "steps"], &[None, None, Some(0), None], &[1, 3]);
+    let data = [
+        1, 300, 0, 10, 0,
+        12, 12, 0, 0, 0,
+        4, 200, 0, 50, 0,
+        1, 0xFFFF_FFFF, 2, 0, 0,
+    ];
+
+    let scopes = decode(&info, &data).unwrap();
+    assert_eq!(scopes, vec![scope("main", 1, None, 10, 300, 100), scope("loop", 4, Some(0), 50, 200, 200)]);
+
+    let counters = decode_counters(&info, &data).unwrap();
+    assert_eq!(counters, vec![
+        CounterResult { name: "iterations".to_string(), hits: 12, value: 12 },
+        CounterResult { name: "steps".to_string(), hits: 1, value: 0x2_FFFF_FFFF },
+    ]);
+}
+
+#[test]
+fn decode_aggregate_counters() {
+    let info = aggregate_info(&["main", "misses"], &[None, None], &[1]);
+    let bytes: Vec<u8> = [64u32, 640, 0, !8, 12, 20, 20, 0, 0, 0].iter().flat_map(|word| word.to_ne_bytes()).collect();
+
+    assert_eq!(decode_aggregate_bytes(&info, &bytes).unwrap().len(), 1);
+    assert_eq!(decode_counters_bytes(&info, &bytes).unwrap(), vec![CounterResult { name: "misses".to_string(), hits: 20, value: 20 }]);
+}
+
+#[test]
+fn decode_printf_lines() {
+    let info = printf_info(4, &["pos %v2u value %5.2f", "zero %d\\n"]);
+    let data = [
+        2,
+        0, 3, 4, 2.5f32.to_bits(),
+        1, -7i32 as u32, 0, 0,
+        0, 0, 0, 0,
+    ];
+
+    assert_eq!(decode_printf(&info, &data).unwrap(), vec!["pos 3, 4 value  2.50", "zero -7\n"]);
+}
+
+#[test]
+fn decode_printf_conversions() {
+    let info = printf_info(9, &["[%+05d|%-4u|%#x|%X|%o|%c|%.3e|%g]"]);
+    let values = [42, 7, 255, 255, 8, 'A' as u32, 1234.5f32.to_bits(), 0.0001f32.to_bits()];
+    let data: Vec<u32> = [1, 0].into_iter().chain(values).collect();
+
+    assert_eq!(decode_printf(&info, &data).unwrap(), vec!["[+0042|7   |0xff|FF|10|A|1.234e+03|0.0001]"]);
+
+    let info = printf_info(5, &["%g %g %G %f"]);
+    let data = [1, 0, 1e-5f32.to_bits(), 100000.0f32.to_bits(), 1e6f32.to_bits(), f32::NEG_INFINITY.to_bits()];
+    assert_eq!(decode_printf(&info, &data).unwrap(), vec!["1e-05 100000 1E+06 -inf"]);
+}
+
+#[test]
+fn decode_printf_ring() {
+    let info = printf_info(2, &["line %u"]);
+    // Five lines into a buffer with room for three, the last two overwrote the first two.
+    let data = [5, 0, 3, 0, 4, 0, 2];
+    assert_eq!(decode_printf(&info, &data).unwrap(), vec!["line 2", "line 3", "line 4"]);
+
+    let bytes: Vec<u8> = [0u32, 0, 0].iter().flat_map(|word| word.to_ne_bytes()).collect();
+    assert_eq!(decode_printf_bytes(&info, &bytes).unwrap(), Vec::<String>::new());
+}
+
+#[test]
+fn decode_printf_errors() {
+    assert_eq!(decode_printf(&info(&["main"], &[None], &[]), &[0; 4]), Err(DecodeError::PrintfDisabled));
+    assert_eq!(decode_printf(&printf_info(3, &[]), &[0, 0]), Err(DecodeError::BufferTooSmall { expected: 4, found: 2 }));
+    assert_eq!(decode_printf(&printf_info(1, &["a"]), &[1, 5]), Err(DecodeError::UnknownFormat { id: 5 }));
+}
+
+fn nested_capture() -> Vec<ScopeResult> {
+    vec![
+        scope("main", 1, None, 1000, 500, 200),
+        scope("loop", 4, Some(0), 1100, 200, 200),
+        scope("shade", 4, Some(0), 1200, 100, 100),
+        scope("never", 0, Some(0), 0, 0, 0),
+    ]
+}
+
+#[test]
+fn export_chrome_trace() {
+    let trace = chrome_trace(&[nested_capture()], 100.0);
+
+    assert_eq!(trace, concat!(
+        r#"{"traceEvents":["#,
+        r#"{"name":"main","cat":"shader","ph":"X","ts":0,"dur":5,"pid":0,"tid":0,"args":{"frame":0,"hits":1,"self_ticks":200}},"#,
+        r#"{"name":"loop","cat":"shader","ph":"X","ts":0,"dur":2,"pid":0,"tid":0,"args":{"frame":0,"hits":4,"self_ticks":200}},"#,
+        r#"{"name":"shade","cat":"shader","ph":"X","ts":2,"dur":1,"pid":0,"tid":0,"args":{"frame":0,"hits":4,"self_ticks":100}}"#,
+        r#"],"displayTimeUnit":"ns"}"#,
+    ));
+}
+
+#[test]
+fn export_chrome_trace_frames() {
+    let second_frame = vec![scope("main", 1, None, 3000, 400, 300), scope("\"quoted\"", 1, Some(0), 3100, 100, 100)];
+    let trace = chrome_trace(&[nested_capture(), second_frame], 100.0);
+
+    assert!(trace.contains(r#"{"name":"main","cat":"shader","ph":"X","ts":20,"dur":4,"pid":0,"tid":0,"args":{"frame":1,"hits":1,"self_ticks":300}}"#));
+    assert!(trace.contains(r#""name":"\"quoted\"""#));
+}
+
+#[test]
+fn export_speedscope() {
+    let profile = speedscope(&[nested_capture()], 100.0);
+
+    assert!(profile.contains(r#""shared":{"frames":[{"name":"main"},{"name":"loop"},{"name":"shade"}]}"#));
+    assert!(profile.contains(concat!(
+        r#"{"type":"evented","name":"frame 0","unit":"microseconds","startValue":0,"endValue":5,"events":["#,
+        r#"{"type":"O","frame":0,"at":0},{"type":"O","frame":1,"at":0},{"type":"C","frame":1,"at":2},"#,
+        r#"{"type":"O","frame":2,"at":2},{"type":"C","frame":2,"at":3},{"type":"C","frame":0,"at":5}"#,
+        r#"]}"#,
+    )));
+}
+
+#[test]
+fn export_speedscope_function_scope() {
+    // A scope without parent that starts inside main is drawn inside it.
+    let scopes = vec![scope("main", 1, None, 1000, 500, 200), scope("loop", 1, Some(0), 1100, 300, 300), scope("shade", 1, None, 1350, 100, 100)];
+    let profile = speedscope(&[scopes], 100.0);
+
+    assert!(profile.contains(concat!(
+        r#"{"type":"O","frame":0,"at":0},{"type":"O","frame":1,"at":0},{"type":"C","frame":1,"at":3},"#,
+        r#"{"type":"O","frame":2,"at":3.5},{"type":"C","frame":2,"at":4.5},{"type":"C","frame":0,"at":5}"#,
+    )));
+}
+
+#[test]
+fn export_speedscope_clamps_children() {
+    // Children of a scope can't take longer than the scope itself.
+    let scopes = vec![scope("main", 1, None, 0, 300, 0), scope("child", 1, Some(0), 0, 200, 200), scope("late", 1, Some(0), 0, 200, 200)];
+    let profile = speedscope(&[scopes], 100.0);
+
+    assert!(profile.contains(concat!(
+        r#"{"type":"O","frame":0,"at":0},{"type":"O","frame":1,"at":0},{"type":"C","frame":1,"at":2},"#,
+        r#"{"type":"O","frame":2,"at":2},{"type":"C","frame":2,"at":3},{"type":"C","frame":0,"at":3}"#,
+    )));
+}
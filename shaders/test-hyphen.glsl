@@ -0,0 +1 @@
+#define HYPHEN_COLOR vec4(pos, 0.0, 1.0)
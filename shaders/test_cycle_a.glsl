@@ -0,0 +1,3 @@
+#include "test_cycle_b.glsl"
+
+#define CYCLE_A_COLOR vec4(0.0)
@@ -0,0 +1,3 @@
+#include "test_cycle_a.glsl"
+
+#define CYCLE_B_COLOR vec4(1.0)
@@ -0,0 +1,8 @@
+#ifndef TEST_GUARD_A
+#define TEST_GUARD_A
+
+#include "test_guard_b.glsl"
+
+#define GUARD_A_COLOR vec4(pos, 0.0, 1.0)
+
+#endif
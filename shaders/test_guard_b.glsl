@@ -0,0 +1,8 @@
+#ifndef TEST_GUARD_B
+#define TEST_GUARD_B
+
+#include "test_guard_a.glsl"
+
+#define GUARD_B_COLOR vec4(pos, 1.0, 1.0)
+
+#endif
@@ -0,0 +1,5 @@
+#pragma once
+
+vec4 pragma_once_color(uvec2 pos) {
+    return vec4(pos, 0.0, 1.0);
+}
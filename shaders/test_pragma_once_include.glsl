@@ -0,0 +1,5 @@
+#include "test_pragma_once.glsl"
+
+vec4 pragma_once_color_inverted(uvec2 pos) {
+    return vec4(1.0) - pragma_once_color(pos);
+}
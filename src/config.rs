@@ -0,0 +1,260 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Section of a crate's Cargo.toml with crate-wide settings for all `glsl!` marcos.
+pub const CONFIG_SECTION: &str = "package.metadata.glsl_compiler";
+
+/// Crate-wide settings read from `[package.metadata.glsl_compiler]`:
+/// ```toml
+/// [package.metadata.glsl_compiler]
+/// include_dirs = ["assets/shaders/common"]
+/// include_root = "shaders"
+/// ```
+/// `include_root` is the directory the crate publishes to crates depending on it as `#include <crate_name/...>`.
+#[derive(Default, Debug)]
+pub struct CrateConfig {
+    pub include_dirs: Vec<String>,
+    pub include_root: Option<String>,
+}
+
+impl CrateConfig {
+    /// Adds the Cargo.toml to `manifests` if it exists.
+    pub fn load(manifest_dir: &Path, manifests: &mut Vec<PathBuf>) -> CrateConfig {
+        let sections = read_sections(&manifest_dir.join("Cargo.toml"), manifests);
+        let section = sections.iter().find(|(header, _)| header == CONFIG_SECTION);
+        if section.is_none() {
+            return CrateConfig::default()
+        }
+        let (_, section) = section.unwrap();
+
+        CrateConfig {
+            include_dirs: string_array(section, "include_dirs"),
+            include_root: string_value(section, "include_root"),
+        }
+    }
+}
+
+/// Include roots of the crate at `manifest_dir` and its path dependencies, as `(crate_name, directory)`.
+/// Workspace dependencies (`dep = { workspace = true }`) are looked up in the workspace's Cargo.toml.
+/// Every Cargo.toml read on the way is added to `manifests`.
+pub fn dependency_include_roots(manifest_dir: &Path, manifests: &mut Vec<PathBuf>) -> Vec<(String, PathBuf)> {
+    let sections = read_sections(&manifest_dir.join("Cargo.toml"), manifests);
+    let workspace = workspace_dependencies(manifest_dir, manifests);
+
+    let mut crates = vec![];
+    if let Some((_, package)) = sections.iter().find(|(header, _)| header == "package") {
+        if let Some(name) = string_value(package, "name") {
+            crates.push((name, manifest_dir.to_path_buf()));
+        }
+    }
+    for (name, path, is_workspace) in dependencies(&sections) {
+        let path = if is_workspace {
+            workspace.iter().find(|(dep, _, _)| *dep == name).map(|(_, path, _)| path.clone())
+        } else {
+            path.map(|path| manifest_dir.join(path))
+        };
+        if let Some(path) = path {
+            crates.push((name, path));
+        }
+    }
+
+    crates.into_iter()
+        .filter_map(|(name, dir)| {
+            let include_root = CrateConfig::load(&dir, manifests).include_root?;
+            Some((name.replace('-', "_"), dir.join(include_root)))
+        })
+        .collect()
+}
+
+/// `[workspace.dependencies]` of the closest workspace root above `manifest_dir`, with absolute paths.
+fn workspace_dependencies(manifest_dir: &Path, manifests: &mut Vec<PathBuf>) -> Vec<(String, PathBuf, bool)> {
+    for dir in manifest_dir.ancestors() {
+        let sections = read_sections(&dir.join("Cargo.toml"), manifests);
+        if !sections.iter().any(|(header, _)| header == "workspace") {
+            continue
+        }
+
+        let workspace_sections: Vec<(String, String)> = sections.into_iter()
+            .filter_map(|(header, body)| header.strip_prefix("workspace.").map(|header| (header.to_string(), body)))
+            .collect();
+        return dependencies(&workspace_sections)
+            .into_iter()
+            .filter_map(|(name, path, _)| path.map(|path| (name, dir.join(path), false)))
+            .collect()
+    }
+    vec![]
+}
+
+/// The dependencies of all dependency tables as `(name, path, workspace = true)`.
+fn dependencies(sections: &[(String, String)]) -> Vec<(String, Option<String>, bool)> {
+    let is_dependency_table = |header: &str| {
+        let table = header.rsplit('.').next().unwrap_or_default();
+        ["dependencies", "dev-dependencies", "build-dependencies"].contains(&table)
+    };
+
+    let mut dependencies = vec![];
+    for (header, body) in sections {
+        if is_dependency_table(header) {
+            // dep = { path = "../dep" } or dep = "1.0"
+            for line in body.lines() {
+                let (name, value) = match line.split_once('=') {
+                    Some((name, value)) => (name.trim().trim_matches('"'), value),
+                    None => continue,
+                };
+                if name.is_empty() || name.contains('.') {
+                    continue
+                }
+                dependencies.push((name.to_string(), string_value(value, "path"), is_workspace(value)));
+            }
+            continue
+        }
+
+        // [dependencies.dep] tables
+        let table = header.rsplit_once('.');
+        if let Some((table_header, name)) = table {
+            if is_dependency_table(table_header) {
+                let name = name.trim_matches('"').to_string();
+                dependencies.push((name, string_value(body, "path"), is_workspace(body)));
+            }
+        }
+    }
+    dependencies
+}
+
+fn is_workspace(value: &str) -> bool {
+    value.split([',', '{', '}', '\n'])
+        .any(|entry| entry.split_once('=').is_some_and(|(key, value)| key.trim() == "workspace" && value.trim() == "true"))
+}
+
+/// The sections of a toml file as `(header, body)` without comments. The part before the first header has an empty header.
+/// Adds `path` to `read` if it could be read.
+fn read_sections(path: &Path, read: &mut Vec<PathBuf>) -> Vec<(String, String)> {
+    let content = fs::read_to_string(path);
+    if content.is_ok() && !read.iter().any(|file| file == path) {
+        read.push(path.to_path_buf());
+    }
+    let content = content.unwrap_or_default();
+
+    let mut sections = vec![(String::new(), String::new())];
+    for line in content.lines() {
+        let line = without_comment(line);
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') && trimmed.ends_with(']') {
+            let header = trimmed.trim_matches(['[', ']']).trim();
+            sections.push((header.to_string(), String::new()));
+        } else {
+            let body = &mut sections.last_mut().unwrap().1;
+            body.push_str(line);
+            body.push('\n');
+        }
+    }
+    sections
+}
+
+/// The line up to a `#` that isn't inside a string.
+fn without_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match quote {
+            // Only "basic strings" have escapes, 'literal strings' don't.
+            Some('"') if escaped => escaped = false,
+            Some('"') if c == '\\' => escaped = true,
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == '#' => return &line[..i],
+            None => {}
+        }
+    }
+    line
+}
+
+/// Finds `key` at the start of a line or of an inline table entry.
+fn key_position(text: &str, key: &str) -> Option<usize> {
+    text.match_indices(key)
+        .map(|(i, _)| i)
+        .find(|i| {
+            let before = text[..*i].trim_end_matches([' ', '\t']);
+            let starts_entry = before.is_empty() || before.ends_with(['\n', '{', ',']);
+            starts_entry && text[(i + key.len())..].trim_start().starts_with('=')
+        })
+}
+
+/// Parses `key = "value"` out of a toml section or inline table.
+pub fn string_value(text: &str, key: &str) -> Option<String> {
+    let rest = &text[key_position(text, key)?..];
+    let value = rest.split_once('=')?.1.trim_start().strip_prefix('"')?;
+    Some(value[..value.find('"')?].to_string())
+}
+
+/// Parses `key = ["a", "b"]` out of a toml section.
+pub fn string_array(section: &str, key: &str) -> Vec<String> {
+    let key_start = key_position(section, key);
+    if key_start.is_none() {
+        return vec![]
+    }
+    let rest = &section[key_start.unwrap()..];
+
+    let array_end = rest.find(']');
+    if array_end.is_none() {
+        return vec![]
+    }
+    string_list(&rest[..=array_end.unwrap()])
+}
+
+/// Parses a list of string literals `["a", "b"]`, as written in toml or in the marco.
+pub fn string_list(text: &str) -> Vec<String> {
+    let array_start = text.find('[');
+    let array_end = text.rfind(']');
+    if array_start.is_none() || array_end.is_none() {
+        return vec![]
+    }
+
+    text[(array_start.unwrap() + 1)..array_end.unwrap()]
+        .split(',')
+        .map(|value| value.trim().trim_matches('"').to_string())
+        .filter(|value| !value.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh directory under the system temp dir with the given files.
+    fn crate_dir(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("glsl_compiler_config_{name}_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        for (file, content) in files {
+            let path = dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn comments_outside_of_strings() {
+        assert_eq!(without_comment("include_root = \"shaders#v2\" # published"), "include_root = \"shaders#v2\" ");
+        assert_eq!(without_comment("path = '..#dep' # \"quoted\""), "path = '..#dep' ");
+        assert_eq!(without_comment("name = \"a\\\"#b\""), "name = \"a\\\"#b\"");
+        assert_eq!(without_comment("# [package.metadata.glsl_compiler]"), "");
+    }
+
+    #[test]
+    fn hashes_in_values() {
+        let dir = crate_dir("hashes", &[
+            ("Cargo.toml", "[package]\nname = \"app\"\n\n[dependencies]\nshared = { path = \"libs/shared#2\" } # local\n"),
+            ("libs/shared#2/Cargo.toml", "[package.metadata.glsl_compiler]\ninclude_root = \"shaders#v2\" # headers\n"),
+        ]);
+
+        let mut manifests = vec![];
+        let roots = dependency_include_roots(&dir, &mut manifests);
+        assert_eq!(roots, [("shared".to_string(), dir.join("libs/shared#2").join("shaders#v2"))]);
+        assert!(manifests.contains(&dir.join("Cargo.toml")));
+        assert!(manifests.contains(&dir.join("libs/shared#2").join("Cargo.toml")));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,439 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::{env, fs, io};
+use shaderc::{IncludeCallbackResult, IncludeType, ResolvedInclude};
+use crate::MARCO_FILE_PATH;
+use crate::config::{dependency_include_roots, CrateConfig};
+use crate::std_lib::{std_lib_header, std_lib_header_names, STD_LIB_PREFIX};
+use crate::rust_source::{find_include_marcos, IncludeMarco};
+use crate::preprocessor::{logical_lines, split_directive, Preprocessor};
+
+/// Maximum nesting of `#include`s, unless the marco sets `max_include_depth`.
+pub const DEFAULT_MAX_INCLUDE_DEPTH: usize = 64;
+
+/// An `#include` that could not be expanded, at the byte `offset` of the `#include` line in the top level source.
+#[derive(Debug)]
+pub struct IncludeError {
+    pub message: String,
+    pub offset: usize,
+}
+
+/// Prefix of includes of `type = Include` marcos: `#include "rust:src/main.rs#included.glsl"`.
+const RUST_INCLUDE_SCHEME: &str = "rust:";
+
+/// Resolves `file =` paths and `#include`s of one `glsl!` invocation and records every include it resolved.
+pub struct IncludeContext {
+    /// Directory of the invoking crate's Cargo.toml.
+    manifest_dir: PathBuf,
+    /// The Rust source file that contains the marco.
+    source_file: Option<PathBuf>,
+    /// `include_dirs` of the marco followed by the crate-wide ones, searched in order.
+    include_dirs: Vec<PathBuf>,
+    max_include_depth: usize,
+    /// Include roots published by this crate and its path dependencies, for `#include <crate_name/...>`.
+    include_roots: Vec<(String, PathBuf)>,
+    pub resolved_includes: RefCell<Vec<ResolvedInclude>>,
+    /// Which search location every `#include` was resolved from, for the build log.
+    pub include_origins: RefCell<Vec<String>>,
+    /// Every file read for the shader, to register them as build dependencies.
+    pub read_files: RefCell<Vec<PathBuf>>,
+    /// Resolved names of the files shaderc is currently including, outermost first.
+    include_stack: RefCell<Vec<String>>,
+    /// Canonical paths of included files with a `#pragma once`, resolved names for bundled and Rust includes.
+    included_once: RefCell<HashSet<String>>,
+}
+
+impl IncludeContext {
+    pub fn new(marco_include_dirs: &[String], max_include_depth: usize) -> IncludeContext {
+        let current_dir = env::current_dir().unwrap_or_default();
+        let manifest_dir = env::var("CARGO_MANIFEST_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| current_dir.clone());
+
+        let source_file = proc_macro::Span::call_site()
+            .local_file()
+            .map(|file| current_dir.join(file));
+
+        // The Cargo.toml files are read files too, changing an include_root has to rebuild the shader.
+        let mut manifests = vec![];
+        let config = CrateConfig::load(&manifest_dir, &mut manifests);
+        let include_roots = dependency_include_roots(&manifest_dir, &mut manifests);
+        let include_dirs = marco_include_dirs.iter()
+            .chain(config.include_dirs.iter())
+            .map(|dir| manifest_dir.join(dir))
+            .collect();
+
+        IncludeContext {
+            manifest_dir,
+            source_file,
+            include_dirs,
+            max_include_depth,
+            include_roots,
+            resolved_includes: RefCell::new(vec![]),
+            include_origins: RefCell::new(vec![]),
+            read_files: RefCell::new(manifests),
+            include_stack: RefCell::new(vec![]),
+            included_once: RefCell::new(HashSet::new()),
+        }
+    }
+
+    /// Candidate locations of a path written in the marco: relative to the manifest directory, then to the Rust source file.
+    fn candidates(&self, path: &str) -> Vec<PathBuf> {
+        let mut candidates = vec![self.manifest_dir.join(path)];
+        if let Some(source_dir) = self.source_file.as_ref().and_then(|file| file.parent()) {
+            let candidate = source_dir.join(path);
+            if !candidates.contains(&candidate) {
+                candidates.push(candidate);
+            }
+        }
+        candidates
+    }
+
+    /// Finds the first existing candidate or lists all searched locations in the error.
+    pub fn resolve_path(&self, path: &str) -> Result<PathBuf, String> {
+        let candidates = self.candidates(path);
+        if let Some(found) = candidates.iter().find(|candidate| candidate.exists()) {
+            return Ok(found.to_path_buf())
+        }
+
+        let searched: Vec<String> = candidates.iter().map(|c| c.display().to_string()).collect();
+        Err(format!("The File {path} could not be found. Searched in: {}", searched.join(", ")))
+    }
+
+    /// Search locations of a GLSL `#include`, labeled for the build log.
+    /// `"quoted"` includes are searched relative to the including file first, `<angle>` includes only in the include directories.
+    fn include_candidates(&self, path: &str, include_type: IncludeType, origen_path: &str) -> Vec<(PathBuf, String)> {
+        let mut candidates = vec![];
+        if include_type == IncludeType::Relative {
+            if origen_path != MARCO_FILE_PATH {
+                let parent = Path::new(origen_path).parent().unwrap_or(Path::new(""));
+                candidates.push((parent.join(path), format!("relative to {origen_path}")));
+            } else {
+                candidates.extend(self.candidates(path).into_iter().map(|c| (c, "relative to the marco".to_string())));
+            }
+        }
+
+        for dir in &self.include_dirs {
+            candidates.push((dir.join(path), format!("in include dir {}", dir.display())));
+        }
+
+        if let Some((crate_name, path_in_root)) = path.split_once('/') {
+            for (_, root) in self.include_roots.iter().filter(|(name, _)| name == crate_name) {
+                candidates.push((root.join(path_in_root), format!("in the include root of {crate_name} {}", root.display())));
+            }
+        }
+        candidates
+    }
+
+    /// Reads a file and records it as a build dependency.
+    pub fn read_file(&self, path: &Path) -> io::Result<String> {
+        let content = fs::read_to_string(path)?;
+        let mut read_files = self.read_files.borrow_mut();
+        if !read_files.iter().any(|file| file == path) {
+            read_files.push(path.to_path_buf());
+        }
+        Ok(content)
+    }
+
+    /// Expands the `#include`s of `source` that shaderc would expand, for passes that need the whole source like `profile`.
+    pub fn manually_include(&self, source: &str, path: &str) -> Result<String, IncludeError> {
+        let mut preprocessor = Preprocessor::new();
+        self.expand_includes(source, path, &mut vec![], &mut preprocessor)
+    }
+
+    /// `chain` holds the resolved names of the files currently being expanded, outermost first.
+    fn expand_includes(&self, source: &str, path: &str, chain: &mut Vec<String>, preprocessor: &mut Preprocessor) -> Result<String, IncludeError> {
+        let mut expansions = vec![];
+        for line in logical_lines(source) {
+            let directive = split_directive(&line.text);
+            if directive.is_none() {
+                continue
+            }
+            let (name, rest) = directive.unwrap();
+
+            if name != "include" {
+                preprocessor.directive(name, rest);
+                continue
+            }
+            if !preprocessor.is_active() {
+                continue
+            }
+
+            let indent = line.text.len() - line.text.trim_start().len();
+            let error = |message: String| IncludeError { message, offset: line.range.start + indent };
+
+            let (include_file_path, include_type) = if rest.len() > 1 && rest.starts_with('"') && rest.ends_with('"') {
+                (&rest[1..(rest.len() - 1)], IncludeType::Relative)
+            } else if rest.len() > 1 && rest.starts_with('<') && rest.ends_with('>') {
+                (&rest[1..(rest.len() - 1)], IncludeType::Standard)
+            } else {
+                return Err(error(format!("#include must have a path in \"\" or <> in the line: {}", &source[line.range.clone()])))
+            };
+
+            let res = self.handle_include(include_file_path, include_type, path, chain.len() + 1);
+            if res.is_err() {
+                return Err(error(format!("#include error: {}", res.err().unwrap())))
+            }
+            let res = res.unwrap();
+
+            // Entering a file again is a cycle, unless its include guard leaves none of its #includes active.
+            if chain.contains(&res.resolved_name) && has_active_include(&res.content, preprocessor) {
+                let mut names = chain.clone();
+                names.push(res.resolved_name);
+                return Err(error(format!("#include cycle: {}", self.chain_text(&names))))
+            }
+
+            chain.push(res.resolved_name.clone());
+            let include_content = self.expand_includes(&res.content, &res.resolved_name, chain, preprocessor);
+            chain.pop();
+            let include_content = include_content.map_err(|err| error(err.message))?;
+            expansions.push((line.range, include_content));
+        }
+
+        let mut source = source.to_string();
+        for (range, include_content) in expansions.into_iter().rev() {
+            source.replace_range(range, &include_content);
+        }
+        Ok(source)
+    }
+
+    /// `a.glsl -> b.glsl -> a.glsl`, cut after the first file that repeats. Paths are relative to the manifest directory.
+    fn chain_text(&self, names: &[String]) -> String {
+        let repeat = (0..names.len()).find(|i| names[..*i].contains(&names[*i]));
+        let names = &names[..repeat.map(|i| i + 1).unwrap_or(names.len())];
+
+        let names: Vec<String> = names.iter()
+            .map(|name| Path::new(name)
+                .strip_prefix(&self.manifest_dir)
+                .map(|path| path.display().to_string())
+                .unwrap_or(name.to_string()))
+            .collect();
+        names.join(" -> ")
+    }
+
+    /// Include callback for shaderc. Also used by `manually_include`.
+    pub fn handle_include(&self, path: &str, include_type: IncludeType, file_path: &str, depth: usize) -> IncludeCallbackResult {
+        // shaderc includes depth first, so the files of lower depth on the stack are the ones including this one.
+        let mut include_stack = self.include_stack.borrow_mut();
+        include_stack.truncate(depth.saturating_sub(1));
+        if depth > self.max_include_depth {
+            let mut chain = include_stack.clone();
+            chain.push(path.to_string());
+            return Err(format!("Include Error Maximum include depth of {} exceeded: {}", self.max_include_depth, self.chain_text(&chain)))
+        }
+        drop(include_stack);
+
+        let res = if let Some(rust_path) = path.strip_prefix(RUST_INCLUDE_SCHEME) {
+            let name_start = rust_path.rfind('#');
+            if name_start.is_none() {
+                return Err(format!("Include Error The Path {path} has no \"#<name>\". Write: {RUST_INCLUDE_SCHEME}<rust file or module>#<name>"))
+            }
+            let name_start = name_start.unwrap();
+            self.resolve_rust_path(&rust_path[..name_start])
+                .and_then(|rust_file| self.handle_rust_include(&rust_file, &rust_path[(name_start + 1)..]))
+        } else {
+            let res = self.handle_glsl_include(path, include_type, file_path);
+            match path.split_once(".rs-") {
+                // Old syntax "src/main.rs-included.glsl", only used when there is no GLSL file with that name.
+                Some((rust_path, name)) if res.is_err() => self.resolve_rust_path(&format!("{rust_path}.rs"))
+                    .and_then(|rust_file| self.handle_rust_include(&rust_file, name))
+                    .map_err(|_| res.err().unwrap()),
+                _ => res,
+            }
+        };
+
+        let mut include = res?;
+        self.include_stack.borrow_mut().push(include.resolved_name.clone());
+        if let Some(content) = strip_pragma_once(&include.content) {
+            // Only the first #include of a #pragma once file gets its content.
+            if !self.included_once.borrow_mut().insert(once_key(&include.resolved_name)) {
+                return Ok(ResolvedInclude {
+                    resolved_name: include.resolved_name,
+                    content: String::new(),
+                })
+            }
+            include.content = content;
+        }
+
+        self.resolved_includes.borrow_mut().push(include.clone());
+        Ok(include)
+    }
+
+    fn handle_glsl_include(&self, file_path: &str, include_type: IncludeType, origen_path: &str) -> IncludeCallbackResult {
+        if let (Some(header), IncludeType::Standard) = (file_path.strip_prefix(STD_LIB_PREFIX), include_type) {
+            let content = std_lib_header(header);
+            if content.is_none() {
+                return Err(format!("Include Error The bundled header <{file_path}> does not exist. Available: {}", std_lib_header_names().join(", ")))
+            }
+            self.include_origins.borrow_mut().push(format!("{file_path} from the bundled standard library"));
+
+            return Ok(ResolvedInclude {
+                resolved_name: format!("<{file_path}>"),
+                content: content.unwrap().to_string(),
+            })
+        }
+
+        let candidates = self.include_candidates(file_path, include_type, origen_path);
+        let found = candidates.iter().find(|(candidate, _)| candidate.exists());
+        if found.is_none() {
+            if candidates.is_empty() {
+                return Err(format!("Include Error The File {file_path} could not be found. No include_dirs are set to search <{file_path}> in."))
+            }
+            let searched: Vec<String> = candidates.iter().map(|(c, _)| c.display().to_string()).collect();
+            return Err(format!("Include Error The File {file_path} could not be found. Searched in: {}", searched.join(", ")))
+        }
+        let (path, origin) = found.unwrap();
+        self.include_origins.borrow_mut().push(format!("{file_path} {origin}"));
+
+        let content = self.read_file(path);
+        if content.is_err() {
+            return Err(format!("Include Error: The File {} could not be read.", path.display()))
+        }
+        let content = content.unwrap();
+
+        Ok(ResolvedInclude {
+            resolved_name: path.display().to_string(),
+            content: content.to_string(),
+        })
+    }
+
+    /// Resolves the Rust file of a `rust:` include: a file path like `src/main.rs`
+    /// or a module path like `self`, `super::shaders` or `crate::render::post`.
+    fn resolve_rust_path(&self, rust_path: &str) -> Result<PathBuf, String> {
+        let first_segment = rust_path.split("::").next().unwrap_or_default();
+        if !["crate", "self", "super"].contains(&first_segment) {
+            return self.resolve_path(rust_path).map_err(|err| format!("Include Error {err}"))
+        }
+
+        // `super` is relative to the module of the marco.
+        let module_path = if first_segment == "super" { format!("self::{rust_path}") } else { rust_path.to_string() };
+
+        // The file of the current module and the directory of its child modules.
+        let mut file = None;
+        let mut dir = PathBuf::new();
+        for segment in module_path.split("::") {
+            let resolved = match segment {
+                "crate" => {
+                    let src_dir = self.manifest_dir.join("src");
+                    let root = ["lib.rs", "main.rs"].iter().map(|root| src_dir.join(root)).find(|root| root.exists());
+                    root.map(|root| (root, src_dir))
+                }
+                "self" => self.source_file.clone().map(|source_file| {
+                    let dir = module_dir(&source_file);
+                    (source_file, dir)
+                }),
+                "super" => dir.parent().and_then(|parent| {
+                    let parent = parent.to_path_buf();
+                    let is_crate_root = parent == self.manifest_dir.join("src");
+                    let candidates = if is_crate_root {
+                        vec![parent.join("lib.rs"), parent.join("main.rs")]
+                    } else {
+                        vec![parent.with_extension("rs"), parent.join("mod.rs")]
+                    };
+                    candidates.into_iter().find(|c| c.exists()).map(|file| (file, parent))
+                }),
+                name => [dir.join(format!("{name}.rs")), dir.join(name).join("mod.rs")]
+                    .into_iter()
+                    .find(|c| c.exists())
+                    .map(|file| (file, dir.join(name))),
+            };
+
+            if resolved.is_none() {
+                return Err(format!("Include Error The Rust module {rust_path} could not be found at {segment}."))
+            }
+            let (module_file, module_dir) = resolved.unwrap();
+            file = Some(module_file);
+            dir = module_dir;
+        }
+
+        file.ok_or(format!("Include Error The Rust module {rust_path} could not be found."))
+    }
+
+    fn handle_rust_include(&self, path: &Path, glsl_macro_name: &str) -> IncludeCallbackResult {
+        let file_path = path.display();
+
+        let content = self.read_file(path);
+        if content.is_err() {
+            return Err(format!("Include Error: The File {file_path} could not be read."))
+        }
+        let content = content.unwrap();
+
+        let marcos: Vec<IncludeMarco> = find_include_marcos(&content)
+            .into_iter()
+            .filter(|marco| marco.name == glsl_macro_name)
+            .collect();
+        if marcos.is_empty() {
+            return Err(format!("Include Error No glsl! marco with type = Include and name = \"{glsl_macro_name}\" in {file_path}."))
+        }
+
+        if marcos.len() > 1 {
+            let locations: Vec<String> = marcos.iter().map(|marco| format!("{file_path}:{}", marco.line)).collect();
+            return Err(format!("Include Error More than one glsl! marco with name = \"{glsl_macro_name}\": {}.", locations.join(", ")))
+        }
+        let glsl_content = &marcos[0].code;
+
+        Ok(ResolvedInclude {
+            resolved_name: format!("{}_glsl_macro_{glsl_macro_name}", path.display()),
+            content: glsl_content.to_string(),
+        })
+    }
+}
+
+/// Whether expanding `source` with the current defines would reach an `#include`.
+fn has_active_include(source: &str, preprocessor: &Preprocessor) -> bool {
+    let mut preprocessor = preprocessor.clone();
+    for line in logical_lines(source) {
+        let directive = split_directive(&line.text);
+        if directive.is_none() {
+            continue
+        }
+        let (name, rest) = directive.unwrap();
+
+        if name != "include" {
+            preprocessor.directive(name, rest);
+        } else if preprocessor.is_active() {
+            return true
+        }
+    }
+    false
+}
+
+/// The `included_once` key of an include: the canonical path for files on disk,
+/// so "x.glsl" and "./x.glsl" count as the same file.
+fn once_key(resolved_name: &str) -> String {
+    fs::canonicalize(resolved_name)
+        .map(|path| path.display().to_string())
+        .unwrap_or_else(|_| resolved_name.to_string())
+}
+
+/// Blanks out the `#pragma once` lines of `content`, keeping the line numbers. `None` if there is none.
+fn strip_pragma_once(content: &str) -> Option<String> {
+    let is_pragma_once = |line: &str| {
+        let directive = line.trim_start().strip_prefix('#');
+        directive.is_some_and(|directive| {
+            let words: Vec<&str> = directive.split_whitespace().collect();
+            words.len() == 2 && words[0] == "pragma" && words[1] == "once"
+        })
+    };
+
+    if !content.lines().any(is_pragma_once) {
+        return None
+    }
+
+    let lines: Vec<&str> = content.split('\n')
+        .map(|line| if is_pragma_once(line) { "" } else { line })
+        .collect();
+    Some(lines.join("\n"))
+}
+
+/// Directory of the child modules of the module in `file`.
+fn module_dir(file: &Path) -> PathBuf {
+    let dir = file.parent().unwrap_or(Path::new("")).to_path_buf();
+    let stem = file.file_stem().and_then(|stem| stem.to_str()).unwrap_or_default();
+    if ["mod", "lib", "main"].contains(&stem) {
+        dir
+    } else {
+        dir.join(stem)
+    }
+}
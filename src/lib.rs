@@ -34,8 +34,10 @@ Mark shader type with `type = <shader type>` in marco.
 
 Possible types
 - Compute
-- Vertex Fragment, Geometry, Mesh
-- RayGeneration, AnyHit, ClosestHit, Miss
+- Vertex Fragment, Geometry, Mesh, Task
+- RayGeneration, AnyHit, ClosestHit, Miss, Intersection, Callable
+
+Mesh, Task and the ray tracing stages are compiled for Vulkan 1.2.
 
 ## Proper Errors 
 ```Rust 
@@ -52,27 +54,114 @@ glsl!{type = Compute, code = {
 ```
 ### will error with: 
 ```shell
-error:  undeclared identifier
+error: undeclared identifier
    |
 13 |             imageStore(img, ivec2(pos), colo);
    |                        ^^^
 
-error:  undeclared identifier
+error: undeclared identifier
    |
 13 |             imageStore(img, ivec2(pos), colo);
    |                                         ^^^^
 
-error:  no matching overloaded function found
+error: no matching overloaded function found
    |
 13 |             imageStore(img, ivec2(pos), colo);
    |             ^^^^^^^^^^
 ```
+Undeclared identifiers and unknown functions also get a `help: did you mean ...?` with the closest identifier
+declared in the shader, its includes or the GLSL built-ins. For the example above that is `color`.
+
+
+## Lints
+`lints = warn` or `lints = deny` checks the shader and its includes for mistakes shaderc accepts but Vulkan doesn't like:
+
+- `missing_binding`: uniforms and buffers without an explicit `binding`
+- `std140_vec3`: `vec3` members in std140 blocks
+- `missing_local_size`: compute shaders without `layout(local_size_x = ...) in;`
+- `read_writeonly_image`: `writeonly` images read with `imageLoad` or `imageAtomic*`
+- `unused_extension`: extensions that are enabled but never used
 
+```Rust
+let (bin, _): (&[u8], &[&str]) = glsl!{type = Compute, lints = deny, code = {
+    #version 450 core
+    layout(local_size_x = 8, local_size_y = 8) in;
+
+    layout(binding = 0) uniform Params {
+        vec3 color; // glsl-lint: allow(std140_vec3)
+        float alpha;
+    } params;
+    ...
+}};
+```
+A `// glsl-lint: allow(<lint>, ...)` comment suppresses lints on its own line or, when it stands alone, in the line or declaration below it.
+
+## Profiling
+`profile` instruments the shader to measure the time of scopes marked with `//PROFILE("<name>");`.
+A scope lasts until the end of the block it is placed in, or until a `return`, `discard` or `break`/`continue` leaves it.
+Returned values are computed before the scope ends. `main` needs a `//PROFILE("main");` at its start.
+
+`profile = functions` gives every function of the shader and its includes a scope named after the function, unless it already starts with one.
+`profile = { functions = ["shade*", "!shade_debug"] }` only profiles the functions matching a pattern and none matching a `!` pattern.
+`*` and `?` are wildcards, `main` is always profiled. Overloads get a scope each, named with their parameter types like `srgb_to_linear(vec4)`.
+
+`//COUNT("<name>");` counts how often it is reached, `COUNT("<name>", <value>);` adds a `uint` value to a counter.
+Counters use the same buffer as the scopes: how often they were reached and the sum of the values as low and high word.
+Their names follow the scope names, `ProfileInfo::counters` lists their ids.
+```Rust
+let (bin, profile_info): (&[u8], &glsl_compiler_runtime::ProfileInfo) = glsl!{type = Compute, profile, code = {
+    #version 450 core
+    void main () {
+        //PROFILE("main");
+        ...
+    }
+}};
+```
+The profiler measures one invocation, selected by the uniform buffer `ProfilerIn { uint active_pixel_x; uint active_pixel_y; }`,
+compared against `gl_GlobalInvocationID.xy` in compute, `gl_FragCoord.xy` in fragment and `gl_LaunchIDEXT.xy` in ray tracing shaders,
+`(gl_WorkGroupID.x, gl_LocalInvocationIndex)` in mesh, `(gl_VertexIndex, gl_InstanceIndex)` in vertex and `(gl_PrimitiveIDIn, gl_InvocationID)` in geometry shaders,
+and writes 5 `uint`s per scope into the storage buffer `ProfilerOut { uint[] data; }`:
+the hit count, the total ticks spent in the scope as low and high word and the last start time.
+They are bound at set 0, binding 10 and 11 by default. Change that with
+`profile = { set = 3, in_binding = 0, out_binding = 1 }`, or pass `ProfilerIn` as push constants with `profile = { push_constant }`
+if the shader has no push constant block of its own.
+
+With `profile` the marco returns a `glsl_compiler_runtime::ProfileInfo` next to the binary, so the crate needs `glsl_compiler_runtime` as dependency.
+It holds the name of every scope and counter by id, the `parents` giving the index of the scope enclosing each scope and the `layout` of the buffers.
+Scopes that are not inside another scope, e.g. in functions, have no parent. Their time is part of the scope they are called from,
+but as that can change between calls it isn't subtracted from the self time of any scope.
+
+`profile = aggregate` measures every invocation instead. Per scope `ProfilerOut` then holds the count, the total ticks as low and high word,
+the minimum as `~ticks` and the maximum, accumulated with atomics, and there is no `ProfilerIn`. Clear the buffer to 0 before every dispatch.
+`profile = { aggregate, subgroup }` first reduces the measurements of a subgroup, so only one invocation per subgroup does atomics.
+Subgroup operations need Vulkan 1.1, so the shader is then compiled for it.
+Decode the buffer with `glsl_compiler_runtime::decode_aggregate`.
+The companion crate `glsl_compiler_runtime` decodes the buffer into the hit count, inclusive and self time in clock ticks of every scope
+with `glsl_compiler_runtime::decode(profile_info, data)`, and exports captures as Chrome trace or speedscope JSON.
+
+`profile = printf` rewrites the `printf("<format>", ...);` and `debugPrintfEXT` calls of the profiled invocation into records of the ring buffer
+`ProfilerPrintf { uint head; uint data[]; }` at binding 12, or `profile = { printf, printf_binding = 4 }`, so it works without validation layer printf support.
+The arguments are evaluated on every invocation, so side effects like `atomicAdd` don't change with printf capture. A `//PROFILE("main");` is only needed with scopes or counters. `ProfileInfo::printf` holds the format strings by id and the record size. Clear `head` to 0 before every dispatch and format the lines with `glsl_compiler_runtime::decode_printf`.
+
+## Build logging
+The marco is silent by default. Set `GLSL_COMPILER_LOG=info` to log the compile time, options and resolved includes of every shader to stderr.
+`GLSL_COMPILER_LOG=debug` also dumps the final shader source. The `print` key enables debug logging for a single marco.
+Every `glsl!` reads the variable through `option_env!`, so changing it recompiles the crates using the marco and logs their shaders again.
+```shell
+GLSL_COMPILER_LOG=info cargo build
+```
 
 ## Just compiling a glsl file at compile time
 ```rust
 let bin: &[u8] = glsl!{type = Compute, file = "shaders/test.glsl"};
 ```
+Paths of `file =` and `#include`s in a marco are resolved relative to the crate's `Cargo.toml` directory first
+and then relative to the Rust source file containing the marco, like `include_str!`.
+Includes inside a glsl file are resolved relative to that file.
+A file with `#pragma once` is only included the first time, `#ifndef` include guards work as usual.
+Every file the marco reads is a build dependency, so changing a shader or any of its includes recompiles it.
+Include cycles without include guards are reported with their chain, e.g. `a.glsl -> b.glsl -> a.glsl`.
+Includes nest at most 64 deep, set `max_include_depth = <number>` to change that.
 
 ## Including Code from other glsl file
 
@@ -91,6 +180,45 @@ let bin: &[u8] = glsl!{type = Compute, code = {
 }};
 ```
 
+## Include directories
+`include_dirs = ["<dir>", ...]` adds directories to search for includes. `"quoted"` includes are searched relative to the including file first
+and then in the include directories, `<angle>` includes only in the include directories, both in the given order.
+```Rust
+let bin: &[u8] = glsl!{type = Compute, include_dirs = ["assets/shaders/common"], code = {
+    #version 450 core
+    #include <post.glsl>
+    ...
+}};
+```
+Directories for every marco of a crate go in its `Cargo.toml` and are searched after the ones of the marco:
+```toml
+[package.metadata.glsl_compiler]
+include_dirs = ["assets/shaders/common"]
+```
+With `GLSL_COMPILER_LOG=debug` the build log shows which directory each include was resolved from.
+
+## Sharing includes between crates
+A crate can publish a directory of GLSL headers in its `Cargo.toml`:
+```toml
+[package.metadata.glsl_compiler]
+include_root = "shaders"
+```
+Crates with a path or workspace dependency on it include them by crate name, e.g. for a `render-core` crate:
+```glsl
+#include <render_core/lighting.glsl>
+```
+
+## Standard library
+The crate ships GLSL headers that can be included with `#include <glsl_compiler/<header>>`:
+- `hash.glsl`: PCG integer hashes and float hashes like `hash12`, `hash33`
+- `random.glsl`: a one-uint random number generator, `rng_seed`, `rng_next_float`, `random_unit_vector`, ...
+- `noise.glsl`: `value_noise`, `gradient_noise` and `fbm`
+- `color.glsl`: sRGB conversion, `luminance`, HSV and tone mapping
+- `packing.glsl`: octahedral normal encoding and 10-10-10-2 color packing
+- `all.glsl`: all of the above
+
+The headers are versioned with the crate.
+
 ## Including Code from other Macro
 
 Example Rust File Name: "src/main.rs"
@@ -99,7 +227,7 @@ fn shader() {
     let bin: &[u8] = glsl!{type = Compute, code = {
         #version 450 core
         
-        #include "src/main.rs-included.glsl"
+        #include "rust:src/main.rs#included.glsl"
     
         layout(binding = 0, rgba8) uniform writeonly image2D img;
         void main () {
@@ -118,20 +246,39 @@ fn included() {
     }};
 }
 ```
+`rust:<path>#<name>` includes the code of the `type = Include` marco with `name = "<name>"`.
+The path is a Rust file like `src/main.rs` or a module path like `self`, `super::shaders` or `crate::render::post`.
 */
 
 mod profiler;
+mod log;
+mod suggest;
+mod tokenizer;
+mod lint;
+mod span_map;
+mod include;
+mod config;
+mod preprocessor;
+mod rust_source;
+mod std_lib;
 
 extern crate proc_macro;
 
-use std::{fs, str};
-use std::path::Path;
-use proc_macro2::{Span, TokenTree};
-use proc_macro_error::{abort_call_site, emit_call_site_error, emit_error, proc_macro_error};
+use std::str;
+use std::time::Instant;
+use proc_macro2::{Delimiter, Group, Span, TokenTree};
+use proc_macro_error::{abort, abort_call_site, proc_macro_error, Diagnostic, Level};
+use quote::ToTokens;
 use std::str::FromStr;
 use std::string::ToString;
-use shaderc::{IncludeCallbackResult, IncludeType, OptimizationLevel, ResolvedInclude};
-use crate::profiler::inject_profiler;
+use shaderc::{EnvVersion, OptimizationLevel, TargetEnv};
+use crate::profiler::{inject_profiler, ProfileOptions};
+use crate::log::{Logger, LOG_ENV};
+use crate::suggest::suggest_identifier;
+use crate::lint::{run_lints, warning_tokens, LintLevel};
+use crate::span_map::SpanIndex;
+use crate::include::{IncludeContext, DEFAULT_MAX_INCLUDE_DEPTH};
+use crate::config::string_list;
 
 enum Token {
     None,
@@ -141,8 +288,11 @@ enum Token {
     File(bool),
     Debug,
     Release,
-    Profile,
+    Profile(bool),
     Print,
+    Lints(bool),
+    IncludeDirs(bool),
+    MaxIncludeDepth(bool),
 }
 
 const MARCO_FILE_PATH: &str = "in_marco";
@@ -173,8 +323,10 @@ Mark shader type with `type = <shader type>` in marco.
 
 Possible types
 - Compute
-- Vertex Fragment, Geometry, Mesh
-- RayGeneration, AnyHit, ClosestHit, Miss
+- Vertex Fragment, Geometry, Mesh, Task
+- RayGeneration, AnyHit, ClosestHit, Miss, Intersection, Callable
+
+Mesh, Task and the ray tracing stages are compiled for Vulkan 1.2.
 */
 #[proc_macro_error(proc_macro_hack)]
 #[proc_macro]
@@ -190,7 +342,11 @@ pub fn glsl(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let mut code_token_tree = None;
     let mut debug = cfg!(debug_assertions);
     let mut profile = false;
+    let mut profile_text = None;
     let mut print = false;
+    let mut lints_text = None;
+    let mut include_dirs_text = None;
+    let mut max_include_depth_text = None;
 
     for token in input.into_iter(){
         let text = token.span().source_text().unwrap();
@@ -214,11 +370,18 @@ pub fn glsl(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
             current_token = Token::Release;
             debug = false;
         } else if text == "profile" {
-            current_token = Token::Profile;
+            current_token = Token::Profile(false);
             profile = true;
         } else if text == "print" {
             current_token = Token::Print;
             print = true;
+        } else if text == "lints" {
+            current_token = Token::Lints(false);
+            lints_text = Some("warn".to_string());
+        } else if text == "include_dirs" {
+            current_token = Token::IncludeDirs(false);
+        } else if text == "max_include_depth" {
+            current_token = Token::MaxIncludeDepth(false);
         } else if text == "file" {
             current_token = Token::File(false);
         } else if text == "=" {
@@ -234,6 +397,18 @@ pub fn glsl(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
                 Token::File(false) => {
                     current_token = Token::File(true);
                 }
+                Token::Lints(false) => {
+                    current_token = Token::Lints(true);
+                }
+                Token::IncludeDirs(false) => {
+                    current_token = Token::IncludeDirs(true);
+                }
+                Token::MaxIncludeDepth(false) => {
+                    current_token = Token::MaxIncludeDepth(true);
+                }
+                Token::Profile(false) => {
+                    current_token = Token::Profile(true);
+                }
                 _ => {}
             }
         } else {
@@ -252,6 +427,18 @@ pub fn glsl(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
                 Token::File(true) => {
                     file_text = Some(text);
                 }
+                Token::Lints(true) => {
+                    lints_text = Some(text);
+                }
+                Token::IncludeDirs(true) => {
+                    include_dirs_text = Some(text);
+                }
+                Token::MaxIncludeDepth(true) => {
+                    max_include_depth_text = Some(text);
+                }
+                Token::Profile(true) => {
+                    profile_text = Some(text);
+                }
                 _ => {}
             }
         }
@@ -259,7 +446,7 @@ pub fn glsl(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     
     // Check type Key
     let type_write_help = "Write: type = <shader type>";
-    let type_possible_value_help = "Possible shader types: Compute, Vertex, Fragment, Geometry, Mesh, RayGeneration, AnyHit, ClosestHit, Miss, Include";
+    let type_possible_value_help = "Possible shader types: Compute, Vertex, Fragment, Geometry, Mesh, Task, RayGeneration, AnyHit, ClosestHit, Miss, Intersection, Callable, Include";
     let glsl_type = match type_token {
         Token::None => {abort_call_site!("Key missing: type"; help=type_write_help; note=type_possible_value_help)}
         Token::Type(false) => {abort_call_site!("Invalid Key: type"; help=type_write_help; note=type_possible_value_help)}
@@ -278,6 +465,8 @@ pub fn glsl(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
                 shaderc::ShaderKind::Geometry
             } else if type_text == Some("Mesh".to_string()) {
                 shaderc::ShaderKind::Mesh
+            } else if type_text == Some("Task".to_string()) {
+                shaderc::ShaderKind::Task
             } else if type_text == Some("RayGeneration".to_string()) {
                 shaderc::ShaderKind::RayGeneration
             } else if type_text == Some("AnyHit".to_string()) {
@@ -286,6 +475,10 @@ pub fn glsl(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
                 shaderc::ShaderKind::ClosestHit
             } else if type_text == Some("Miss".to_string()) {
                 shaderc::ShaderKind::Miss
+            } else if type_text == Some("Intersection".to_string()) {
+                shaderc::ShaderKind::Intersection
+            } else if type_text == Some("Callable".to_string()) {
+                shaderc::ShaderKind::Callable
             } else if type_text == Some("Include".to_string()) {
                 return proc_macro::TokenStream::from_str("()").unwrap()
             } else {
@@ -295,7 +488,33 @@ pub fn glsl(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
         _ => {unreachable!()}
     };
 
-    
+    let lint_level = match lints_text.as_deref() {
+        None => None,
+        Some("warn") => Some(LintLevel::Warn),
+        Some("deny") => Some(LintLevel::Deny),
+        Some(text) => abort_call_site!("Invalid lints Value: {}", text; help="Write: lints = warn or lints = deny"),
+    };
+
+    let include_dirs = match include_dirs_text.as_deref() {
+        None => vec![],
+        Some(text) if text.starts_with('[') => string_list(text),
+        Some(text) => abort_call_site!("Invalid include_dirs Value: {}", text; help="Write: include_dirs = [\"<dir>\", ...]"),
+    };
+
+    let max_include_depth = match max_include_depth_text.as_deref() {
+        None => DEFAULT_MAX_INCLUDE_DEPTH,
+        Some(text) => match text.parse::<usize>() {
+            Ok(depth) => depth,
+            Err(_) => abort_call_site!("Invalid max_include_depth Value: {}", text; help="Write: max_include_depth = <number>"),
+        },
+    };
+
+    let profile_options = match profile_text.as_deref() {
+        None => ProfileOptions::default(),
+        Some(text) => ProfileOptions::parse(text),
+    };
+
+    let include_context = IncludeContext::new(&include_dirs, max_include_depth);
     let (mut source, file_path) = if file_text.is_some(){
         if code_token_tree.is_some() {
             abort_call_site!("Cannot use file = \"<glsl file path>\" and code = <glsl code> in one marco");
@@ -312,15 +531,17 @@ pub fn glsl(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
         }
         let file_path = file_path.unwrap();
 
-        if !Path::new(file_path).exists() {
-            abort_call_site!("The File {} could not be found.", file_path)
+        let resolved_path = include_context.resolve_path(file_path);
+        if resolved_path.is_err() {
+            abort_call_site!("{}", resolved_path.err().unwrap())
         }
+        let resolved_path = resolved_path.unwrap();
 
-        let content = fs::read_to_string(file_path);
+        let content = include_context.read_file(&resolved_path);
         if content.is_err() {
-            abort_call_site!("The File {} could not be read.", file_path)
+            abort_call_site!("The File {} could not be read.", resolved_path.display())
         }
-        (content.unwrap(), file_path.to_string())
+        (content.unwrap(), resolved_path.display().to_string())
     } else {
         let code_write_help = "Write: code = {<glsl>}";
         (match code_token {
@@ -336,22 +557,51 @@ pub fn glsl(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
         }, MARCO_FILE_PATH.to_string())
     };
 
-    let (source, scope_names) = if profile {
-        source = manually_include(&source, &file_path, 0);
-        inject_profiler(source)
+    let mut logger = Logger::new(print);
+    if file_path == MARCO_FILE_PATH {
+        let call_site = proc_macro::Span::call_site();
+        logger.set_shader(&format!("{}:{}", call_site.file(), call_site.line()));
     } else {
-        (source, vec![])
+        logger.set_shader(&file_path);
+    }
+    let options_text = format!("{glsl_type:?}, {}{}",
+        if debug { "debug" } else { "release" },
+        if profile { ", profile" } else { "" });
+
+    let start_time = Instant::now();
+    let original_source = source.clone();
+
+    let (source, profile_info) = if profile {
+        let included = include_context.manually_include(&source, &file_path);
+        if included.is_err() {
+            let error = included.err().unwrap();
+            let span = code_token_tree.as_ref().and_then(|tree| SpanIndex::new(tree, &source).span_at(error.offset));
+            if span.is_none() {
+                let line = source[..error.offset].matches('\n').count() + 1;
+                abort_call_site!("{}", error.message; note="{}:{}", file_path, line)
+            }
+            abort!(span.unwrap(), "{}", error.message)
+        }
+        source = included.unwrap();
+        let (source, metadata) = inject_profiler(source, &profile_options, glsl_type);
+        (source, Some(metadata.info_code(&profile_options)))
+    } else {
+        (source, None)
     };
 
-    if print {
-        println!("Shader input {source}");
-    }
+    logger.debug(format_args!("Shader input:\n{source}"));
 
     let compiler = shaderc::Compiler::new().unwrap();
     let mut options = shaderc::CompileOptions::new().unwrap();
 
     // Should not be needed because all #include statements have already been resolved manually.
-    options.set_include_callback(handle_include);
+    options.set_include_callback(|path, include_type, file_path, depth| {
+        include_context.handle_include(path, include_type, file_path, depth)
+    });
+
+    if let Some(version) = target_vulkan_version(glsl_type, profile && profile_options.subgroup) {
+        options.set_target_env(TargetEnv::Vulkan, version as u32);
+    }
 
     if debug {
         options.set_optimization_level(OptimizationLevel::Zero);
@@ -368,261 +618,268 @@ pub fn glsl(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
         "main", Some(&options));
     
     if binary_result.is_err() {
+        logger.info(format_args!("Failed to compile ({options_text}) after {:.2?}.", start_time.elapsed()));
+
         let err = binary_result.err().unwrap().to_string();
-        let err_lines: Vec<_> = err.split(&format!("{file_path}:")).collect();
-        
-        if file_path != MARCO_FILE_PATH || err_lines.len() == 1 {
-            emit_call_site_error!("{}", err);
-        } else {
-            let code_token_tree = code_token_tree.unwrap();
-            for err_line in err_lines.iter().skip(1) {
-                let parts: Vec<_> = err_line.split(":").collect();
-
-                println!("Err Message Parts {parts:?}");
-                let line = parts[0].parse::<usize>();
-                if line.is_err() {
-                    emit_call_site_error!("Error: {}", err_line);
-                    continue;
-                }
-                let line = line.unwrap();
+        let messages: Vec<_> = err.lines().filter_map(parse_shaderc_message).collect();
+        let mut diagnostics = vec![];
 
-                let key = parts[2].strip_prefix(" '").unwrap().strip_suffix("' ");
-                if key.is_none() {
-                    emit_call_site_error!("Error: {}", err_line);
-                    continue;
-                }
-                let key = key.unwrap();
+        let span_index = code_token_tree.as_ref().map(|tree| SpanIndex::new(tree, &original_source));
+        let resolved_includes = include_context.resolved_includes.borrow();
+        let mut declaring_sources = vec![source.as_str()];
+        declaring_sources.extend(resolved_includes.iter().map(|include| include.content.as_str()));
 
-                let (span, _, _) = find_best_line(&source, code_token_tree.clone(), key,0, line - 1);
-                if span.is_some() {
-                    emit_error!(span.unwrap(), "{} {}", parts[3], parts[2]);
-                } else {
-                    emit_call_site_error!("{} {}", parts[3], parts[2])
-                }
-            }
+        for message in messages.iter() {
+            logger.debug(format_args!("Error message: {message:?}"));
+
+            let span = if message.file == MARCO_FILE_PATH && !profile {
+                span_index.as_ref().and_then(|index| index.find(message.line - 1, message.column, &message.key))
+            } else {
+                None
+            };
+
+            diagnostics.push(shaderc_diagnostic(message, span, &declaring_sources));
+        }
+
+        if diagnostics.is_empty() {
+            diagnostics.push(call_site_error(err.to_string()));
         }
 
-        proc_macro::TokenStream::from_str(&format!("panic!(\"{err}\")")).unwrap()
+        with_dependencies(error_output(diagnostics, profile_info.as_deref()), &include_context)
     } else {
+        let binary_result = binary_result.unwrap();
+        logger.info(format_args!("Compiled ({options_text}) in {:.2?}, {} bytes.", start_time.elapsed(), binary_result.len()));
 
-        let mut res = "(&[".to_string();
-        for byte in binary_result.unwrap().as_binary_u8() {
-            res = format!("{res}{byte},");
+        let res = shader_output(binary_result.as_binary_u8(), profile_info.as_deref());
+
+        let resolved_includes = include_context.resolved_includes.borrow();
+        if !resolved_includes.is_empty() {
+            let names: Vec<_> = resolved_includes.iter().map(|include| include.resolved_name.as_str()).collect();
+            logger.info(format_args!("Includes: {}", names.join(", ")));
+        }
+        for origin in include_context.include_origins.borrow().iter() {
+            logger.debug(format_args!("Resolved include {origin}"));
         }
 
-        res = format!("{res}], &[");
+        if lint_level.is_none() {
+            return with_dependencies(proc_macro::TokenStream::from_str(&res).unwrap(), &include_context)
+        }
 
-        for name in scope_names {
-            res = format!("{res}\"{name}\",");
+        let mut lint_sources = vec![original_source.as_str()];
+        lint_sources.extend(resolved_includes.iter().map(|include| include.content.as_str()));
+        let findings = run_lints(&lint_sources, glsl_type);
+        logger.info(format_args!("{} lint findings.", findings.len()));
+
+        let span_index = code_token_tree.as_ref().map(|tree| SpanIndex::new(tree, &original_source));
+        let mut diagnostics = vec![];
+        let mut output = proc_macro2::TokenStream::new();
+        for finding in findings.iter() {
+            let span = match (&span_index, finding.offset) {
+                (Some(index), Some(offset)) if finding.source_index == 0 => index.span_at(offset),
+                _ => None,
+            };
+            let message = finding.message.to_string();
+            let message = if span.is_some() {
+                message
+            } else {
+                let file = if finding.source_index == 0 { &file_path } else { &resolved_includes[finding.source_index - 1].resolved_name };
+                format!("{message} ({file}:{})", finding.line + 1)
+            };
+            let span = span.unwrap_or_else(Span::call_site);
+
+            if lint_level == Some(LintLevel::Deny) {
+                diagnostics.push(Diagnostic::spanned(span, Level::Error, format!("glsl lint `{}`: {message}", finding.lint))
+                    .help(format!("Suppress with // glsl-lint: allow({})", finding.lint)));
+            } else {
+                output.extend(warning_tokens(span, finding.lint, &message));
+            }
         }
-        res = format!("{res}])");
 
-        if debug {
-            println!("   > Compiled shader {} in debug mode.", file_path);
-        } else {
-            println!("   > Compiled shader {} in release mode.", file_path);
+        if !diagnostics.is_empty() {
+            return with_dependencies(error_output(diagnostics, profile_info.as_deref()), &include_context);
         }
 
+        output.extend(proc_macro2::TokenStream::from_str(&res).unwrap());
+        let output = TokenTree::Group(Group::new(Delimiter::Brace, output)).into_token_stream().into();
+        with_dependencies(output, &include_context)
+    }
+}
 
+/// Wraps the output in a block with an `include_bytes!` of every file read for the shader
+/// and an `option_env!` of the log variable, so cargo rebuilds it when one of them changes.
+fn with_dependencies(output: proc_macro::TokenStream, include_context: &IncludeContext) -> proc_macro::TokenStream {
+    let read_files = include_context.read_files.borrow();
 
-        proc_macro::TokenStream::from_str(&res).unwrap()
+    // proc_macro::tracked_env is unstable, but rustc records the variables of option_env! for cargo.
+    let mut res = format!("const _: Option<&str> = option_env!({LOG_ENV:?});");
+    for file in read_files.iter() {
+        res = format!("{res}const _: &[u8] = include_bytes!({:?});", file.display().to_string());
     }
-}
 
-fn find_best_line<'a>(mut source: &'a str, t: TokenTree, key: &'a str, mut current_line: usize, line: usize) -> (Option<Span>, &'a str, usize) {
-    
-    let mut check = |span: Span| {
-        let text = span.source_text().unwrap();
-        let position = source.find(&text);
-        if position.is_none() {
-           return (None, source, current_line)
-        }
-        let position = position.unwrap();
+    let mut tokens = proc_macro2::TokenStream::from_str(&res).unwrap();
+    tokens.extend(proc_macro2::TokenStream::from(output));
+    TokenTree::Group(Group::new(Delimiter::Brace, tokens)).into_token_stream().into()
+}
 
-        let lines = source[..position].chars().filter(|c| *c == '\n').count();
+/// The Vulkan version shaderc has to target, `None` for its default Vulkan 1.0.
+/// Mesh and ray tracing stages need SPIR-V 1.4 (Vulkan 1.2), subgroup operations SPIR-V 1.3 (Vulkan 1.1).
+fn target_vulkan_version(glsl_type: shaderc::ShaderKind, subgroup: bool) -> Option<EnvVersion> {
+    match glsl_type {
+        shaderc::ShaderKind::Mesh | shaderc::ShaderKind::Task
+        | shaderc::ShaderKind::RayGeneration | shaderc::ShaderKind::AnyHit | shaderc::ShaderKind::ClosestHit
+        | shaderc::ShaderKind::Miss | shaderc::ShaderKind::Intersection | shaderc::ShaderKind::Callable => Some(EnvVersion::Vulkan1_2),
+        _ if subgroup => Some(EnvVersion::Vulkan1_1),
+        _ => None,
+    }
+}
 
-        current_line += lines;
-        source = &source[position..];
-        
-        if (key == "" || text.contains(key)) && current_line >= line {
-            //println!("Found Key {text} at: {current_line}");
-            (Some(span), source, current_line)
-        } else {
-            (None, source, current_line)
-        }
-    };
-    
-    match t {
-        TokenTree::Group(g) => {
-            for t in g.stream() {
-                let (res, new_source, new_current_line) = find_best_line(source, t, key, current_line, line);
-                source = new_source;
-                current_line = new_current_line;
-                
-                if res.is_some() {
-                    return (res, source, current_line)
-                }
-            }
-        }
-        TokenTree::Ident(n) => {return check(n.span())}
-        TokenTree::Punct(n) => {return check(n.span())}
-        TokenTree::Literal(n) => {return check(n.span())}
+/// The tuple every successful `glsl!` evaluates to: the SPIR-V binary and, with `profile`, the
+/// `glsl_compiler_runtime::ProfileInfo` of the shader, otherwise an empty `&[&str]`.
+fn shader_output(binary: &[u8], profile_info: Option<&str>) -> String {
+    // The u8 suffix on the first byte types the whole array.
+    let mut res = "(&[".to_string();
+    for (i, byte) in binary.iter().enumerate() {
+        let suffix = if i == 0 { "u8" } else { "" };
+        res = format!("{res}{byte}{suffix},");
     }
 
-    (None, source, current_line)
+    res = format!("{res}] as &'static [u8], ");
+
+    match profile_info {
+        Some(info) => format!("{{ const PROFILE_INFO: ::glsl_compiler_runtime::ProfileInfo = {info}; {res}&PROFILE_INFO) }}"),
+        None => format!("{res}&[] as &'static [&'static str])"),
+    }
 }
 
+/// One `<file>:<line>[:<column>]: error: '<key>' : <message>` line of shaderc output.
+#[derive(Debug)]
+struct ShadercMessage {
+    file: String,
+    line: usize,
+    column: Option<usize>,
+    key: String,
+    message: String,
+}
 
-fn manually_include(source: &str, path: &str, recursion_depth: usize) -> String {
-    let mut source = source.to_string();
+fn parse_shaderc_message(text: &str) -> Option<ShadercMessage> {
+    let (location, rest) = text.split_once(": error: ")?;
 
-    // Find all #include positions
-    let include_positions: Vec<usize> = source
-        .match_indices("#include")
-        .map(|(i, _)|i)
-        .collect();
+    let (head, last) = location.rsplit_once(':')?;
+    let last: usize = last.parse().ok()?;
+    let (file, line, column) = match head.rsplit_once(':') {
+        Some((file, line)) if line.parse::<usize>().is_ok() => (file, line.parse().unwrap(), Some(last)),
+        _ => (head, last, None),
+    };
 
-    for include_position in include_positions.into_iter().rev() {
-        let include_line_end = source[include_position..].find('\n');
-        if include_line_end.is_none() {
-            abort_call_site!("#include has no new line after it"; note=source);
-        }
-        let include_line_end = include_line_end.unwrap() + include_position;
-        let include_line = &source[include_position..include_line_end];
+    let (key, message) = match rest.strip_prefix('\'').and_then(|rest| rest.split_once("' :")) {
+        Some((key, message)) => (key.to_string(), message.trim().to_string()),
+        None => (String::new(), rest.trim().to_string()),
+    };
 
-        let quote_indices: Vec<usize> = include_line
-            .match_indices('"')
-            .map(|(i, _)|i)
-            .collect();
+    Some(ShadercMessage { file: file.to_string(), line: line.max(1), column, key, message })
+}
 
-        if quote_indices.len() != 2 {
-            abort_call_site!("#include must have 2 \" in the line"; note=include_line);
-        }
+/// The diagnostic of a shaderc message at `span`, or at the call site naming the file and line.
+/// Unknown identifiers get the closest identifier declared in `declaring_sources` or built into GLSL as help.
+fn shaderc_diagnostic(message: &ShadercMessage, span: Option<Span>, declaring_sources: &[&str]) -> Diagnostic {
+    let mut diagnostic = match span {
+        Some(span) => Diagnostic::spanned(span, Level::Error, message.message.to_string()),
+        None => call_site_error(format!("{}:{}: '{}' : {}", message.file, message.line, message.key, message.message)),
+    };
 
-        let include_file_path = &include_line[(quote_indices[0] + 1)..quote_indices[1]];
-        let res = handle_include(include_file_path, IncludeType::Relative, path, recursion_depth);
-        if res.is_err() {
-            let error_string = res.err().unwrap();
-            abort_call_site!("#include error: {}", error_string);
+    let unknown_identifier = message.message.contains("undeclared identifier")
+        || message.message.contains("no matching overloaded function found");
+    if unknown_identifier {
+        if let Some(suggestion) = suggest_identifier(&message.key, declaring_sources) {
+            diagnostic = diagnostic.help(format!("did you mean `{suggestion}`?"));
         }
-
-        let res = res.unwrap();
-        let include_source = res.content;
-        let resolved_include_file_path = res.resolved_name;
-        let include_content = manually_include(&include_source, &resolved_include_file_path, recursion_depth + 1);
-
-        source.replace_range(include_position..include_line_end, &include_content);
     }
+    diagnostic
+}
 
-    source
+fn call_site_error(message: String) -> Diagnostic {
+    Diagnostic::spanned(Span::call_site(), Level::Error, message)
 }
 
-fn handle_include(path: &str, _: IncludeType, file_path: &str, _: usize) -> IncludeCallbackResult {
-    
-    let parts: Vec<&str> = path.split('-').collect();
-    if parts.is_empty() || parts.len() == 1 {
-        return handle_glsl_include(path, file_path)
+/// Expands to `compile_error!`s for all diagnostics followed by an empty placeholder of the same type as `shader_output`,
+/// so the surrounding crate keeps type checking while the shader is broken.
+fn error_output(diagnostics: Vec<Diagnostic>, profile_info: Option<&str>) -> proc_macro::TokenStream {
+    let mut output = proc_macro2::TokenStream::new();
+    for diagnostic in diagnostics {
+        diagnostic.to_tokens(&mut output);
     }
+    output.extend(proc_macro2::TokenStream::from_str(&shader_output(&[], profile_info)).unwrap());
 
-    if parts.len() > 2 {
-        return Err(format!("Include Error The Path {path} has more than one \"-\""))
-    }
-    
-    handle_rust_include(parts[0], parts[1])
+    TokenTree::Group(Group::new(Delimiter::Brace, output)).into_token_stream().into()
 }
 
-fn handle_glsl_include(file_path: &str, origen_path: &str) -> IncludeCallbackResult {
-    let path = if origen_path != MARCO_FILE_PATH {
-        let path = Path::new(origen_path);
-        if let Some(parent_path) = path.parent() {
-            format!("{}/{}", parent_path.to_str().unwrap(), file_path)
-        } else {
-            file_path.to_string()
-        }
-    } else {
-        file_path.to_string()
-    };
-    
-    if !Path::new(&path).exists() {
-        return Err(format!("Include Error The File {path} could not be found."))
-    }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let content = fs::read_to_string(&path);
-    if content.is_err() {
-        return Err(format!("Include Error: The File {path} could not be read."))
+    fn parsed(text: &str) -> Option<(String, usize, Option<usize>, String, String)> {
+        parse_shaderc_message(text).map(|m| (m.file, m.line, m.column, m.key, m.message))
     }
-    let content = content.unwrap();
-    
-    Ok(ResolvedInclude {
-        resolved_name: path.to_string(),
-        content: content.to_string(),
-    })
-}
 
-fn handle_rust_include(file_path: &str, glsl_macro_name: &str) -> IncludeCallbackResult {
-    if !Path::new(file_path).exists() {
-        return Err(format!("Include Error The File {file_path} could not be found."))
+    #[test]
+    fn shaderc_message_without_column() {
+        assert_eq!(parsed("in_marco:12: error: 'imgaeStore' : no matching overloaded function found"), Some((
+            "in_marco".to_string(), 12, None, "imgaeStore".to_string(), "no matching overloaded function found".to_string(),
+        )));
+        assert_eq!(parsed("in_marco:0: error: '#version' : must occur first in shader"), Some((
+            "in_marco".to_string(), 1, None, "#version".to_string(), "must occur first in shader".to_string(),
+        )));
     }
 
-    let content = fs::read_to_string(file_path);
-    if content.is_err() {
-        return Err(format!("Include Error: The File {file_path} could not be read."))
+    #[test]
+    fn shaderc_message_with_column() {
+        assert_eq!(parsed("in_marco:7:24: error: 'base_colour' : undeclared identifier"), Some((
+            "in_marco".to_string(), 7, Some(24), "base_colour".to_string(), "undeclared identifier".to_string(),
+        )));
     }
-    let content = content.unwrap();
 
-    let found_indices: Vec<usize> = content.match_indices(&format!("name = \"{glsl_macro_name}\"")).map(|(i, _)|i).collect();
-    if found_indices.is_empty() {
-        return Err(format!("Include Error No glsl! marco with the name = \"{glsl_macro_name}\" in {file_path}."))
+    #[test]
+    fn shaderc_message_in_include() {
+        assert_eq!(parsed("/home/user/shaders/lighting.glsl:3: error: 'COLOUR' : undeclared identifier"), Some((
+            "/home/user/shaders/lighting.glsl".to_string(), 3, None, "COLOUR".to_string(), "undeclared identifier".to_string(),
+        )));
+        assert_eq!(parsed("C:\\shaders\\lighting.glsl:3:5: error: '=' :  cannot convert from ' const float' to ' temp 3-component vector of float'"), Some((
+            "C:\\shaders\\lighting.glsl".to_string(), 3, Some(5), "=".to_string(),
+            "cannot convert from ' const float' to ' temp 3-component vector of float'".to_string(),
+        )));
+        assert_eq!(parsed("<glsl_compiler/noise.glsl>:4: error: 'hash22' : no matching overloaded function found"), Some((
+            "<glsl_compiler/noise.glsl>".to_string(), 4, None, "hash22".to_string(), "no matching overloaded function found".to_string(),
+        )));
     }
 
-    if found_indices.len() > 1 {
-        return Err(format!("Include Error More than one occurrence of name = \"{glsl_macro_name}\" in {file_path}."))
-    }
-    let name_index = found_indices[0];
-    let code_start_index = content[name_index..].find("code = {");
-    if code_start_index.is_none() {
-        return Err(format!("Include Error No opening Brace found! name = \"{glsl_macro_name}\" must be followed by a code = {{<glsl>}}."))
+    #[test]
+    fn shaderc_message_without_key() {
+        assert_eq!(parsed("in_marco:30: error: syntax error, unexpected IDENTIFIER"), Some((
+            "in_marco".to_string(), 30, None, String::new(), "syntax error, unexpected IDENTIFIER".to_string(),
+        )));
+        assert_eq!(parsed("1 error generated."), None);
+        assert_eq!(parsed("in_marco: error: missing line"), None);
     }
-    let code_start_index = code_start_index.unwrap() + name_index + 8;
 
-    let code_end_index = find_closing_bracket(&content[code_start_index..]);
-
-    if code_end_index.is_none() {
-        return Err(format!("Include Error No closing Brace found! name = \"{glsl_macro_name}\" must be followed by a code = {{<glsl>}}. \
-         Start index {code_start_index}. \
-         Searched in {content:?}"))
+    /// The `compile_error!` message `glsl!` expands to for a line of shaderc output.
+    fn compile_error(shaderc_output: &str, declaring_sources: &[&str]) -> String {
+        let message = parse_shaderc_message(shaderc_output).unwrap();
+        let diagnostic = shaderc_diagnostic(&message, None, declaring_sources);
+        diagnostic.into_token_stream().to_string()
     }
-    let code_end_index = code_end_index.unwrap() + code_start_index;
-    let glsl_content = &content[code_start_index..code_end_index];
 
-    //return Err(format!("glsl_content {glsl_content}"));
+    #[test]
+    fn unknown_identifiers_suggest_the_closest() {
+        let shader = "#version 450 core\nlayout(binding = 0) uniform Params {\n    vec3 base_color;\n} params;\n";
 
-    Ok(ResolvedInclude {
-        resolved_name: format!("{file_path}_glsl_macro_{glsl_macro_name}"),
-        content: glsl_content.to_string(),
-    })
-}
+        let error = compile_error("in_marco:9: error: 'imgaeStore' : no matching overloaded function found", &[shader]);
+        assert!(error.contains("in_marco:9: 'imgaeStore' : no matching overloaded function found"), "{error}");
+        assert!(error.contains("= help: did you mean `imageStore`?"), "{error}");
 
-fn find_closing_bracket(content: &str) -> Option<usize> {
-    let mut counter = 1;
-    let mut code_end_index = None;
-    for (offset, val) in content
-        .match_indices(['{', '}'])
-        .into_iter() {
-
-        if val == "{" {
-            counter += 1;
-        } else if val == "}" {
-            counter -= 1;
-        }
+        let error = compile_error("in_marco:7:24: error: 'base_colour' : undeclared identifier", &[shader]);
+        assert!(error.contains("= help: did you mean `base_color`?"), "{error}");
 
-        if counter <= 0 {
-            code_end_index = Some(offset -2);
-            break
-        }
+        let error = compile_error("in_marco:7: error: 'assign' : l-value required \"params\" (can't modify a uniform)", &[shader]);
+        assert!(!error.contains("did you mean"), "{error}");
     }
-
-    code_end_index
 }
-
-
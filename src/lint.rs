@@ -0,0 +1,406 @@
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::quote;
+use shaderc::ShaderKind;
+use crate::tokenizer::{tokenize, GlslToken, TokenKind};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LintLevel {
+    Warn,
+    Deny,
+}
+
+/// A lint hit in `sources[source_index]` at the 0-based `line`, pointing at the token at byte `offset`.
+/// `first_line` is where the declaration starts, which a standalone allow comment above it covers.
+#[derive(Debug)]
+pub struct LintFinding {
+    pub lint: &'static str,
+    pub message: String,
+    pub source_index: usize,
+    pub line: usize,
+    pub first_line: usize,
+    pub offset: Option<usize>,
+}
+
+/// Prefix of the GLSL comment that suppresses lints on its own or on the following line:
+/// `// glsl-lint: allow(missing_binding, std140_vec3)`
+const ALLOW_COMMENT: &str = "glsl-lint: allow(";
+
+const VEC3_TYPES: &[&str] = &["vec3", "ivec3", "uvec3", "bvec3", "dvec3"];
+
+/// Identifiers that prove an extension is used. Entries ending with `*` match as prefix.
+const EXTENSION_IDENTIFIERS: &[(&str, &[&str])] = &[
+    ("GL_EXT_shader_realtime_clock", &["clockRealtime2x32EXT", "clockRealtimeEXT"]),
+    ("GL_ARB_shader_clock", &["clock2x32ARB", "clockARB"]),
+    ("GL_EXT_debug_printf", &["debugPrintfEXT"]),
+    ("GL_EXT_ray_tracing", &["traceRayEXT", "executeCallableEXT", "reportIntersectionEXT", "ignoreIntersectionEXT",
+        "terminateRayEXT", "rayPayloadEXT", "rayPayloadInEXT", "hitAttributeEXT", "callableDataEXT", "callableDataInEXT",
+        "shaderRecordEXT", "accelerationStructureEXT", "gl_*EXT"]),
+    ("GL_EXT_ray_query", &["rayQuery*"]),
+    ("GL_EXT_nonuniform_qualifier", &["nonuniformEXT"]),
+    ("GL_EXT_scalar_block_layout", &["scalar"]),
+    ("GL_EXT_buffer_reference", &["buffer_reference"]),
+    ("GL_EXT_buffer_reference2", &["buffer_reference", "buffer_reference_align"]),
+    ("GL_EXT_mesh_shader", &["SetMeshOutputsEXT", "EmitMeshTasksEXT", "taskPayloadSharedEXT", "gl_*EXT", "max_vertices", "max_primitives"]),
+    ("GL_GOOGLE_include_directive", &["include"]),
+    ("GL_KHR_shader_subgroup_basic", &["subgroup*", "gl_Subgroup*", "gl_NumSubgroups"]),
+    ("GL_KHR_shader_subgroup_vote", &["subgroupAll", "subgroupAny", "subgroupAllEqual"]),
+    ("GL_KHR_shader_subgroup_arithmetic", &["subgroupAdd", "subgroupMul", "subgroupMin", "subgroupMax", "subgroupAnd",
+        "subgroupOr", "subgroupXor", "subgroupInclusive*", "subgroupExclusive*"]),
+    ("GL_KHR_shader_subgroup_ballot", &["subgroupBallot*", "subgroupBroadcast*", "subgroupInverseBallot", "gl_Subgroup*Mask"]),
+    ("GL_KHR_shader_subgroup_shuffle", &["subgroupShuffle", "subgroupShuffleXor"]),
+    ("GL_KHR_shader_subgroup_shuffle_relative", &["subgroupShuffleUp", "subgroupShuffleDown"]),
+    ("GL_EXT_shader_explicit_arithmetic_types", &["int8_t", "uint8_t", "int16_t", "uint16_t", "int64_t", "uint64_t",
+        "float16_t", "float64_t", "i8vec*", "u8vec*", "i16vec*", "u16vec*", "i64vec*", "u64vec*", "f16vec*", "f16mat*"]),
+    ("GL_EXT_shader_explicit_arithmetic_types_int8", &["int8_t", "uint8_t", "i8vec*", "u8vec*"]),
+    ("GL_EXT_shader_explicit_arithmetic_types_int16", &["int16_t", "uint16_t", "i16vec*", "u16vec*"]),
+    ("GL_EXT_shader_explicit_arithmetic_types_int64", &["int64_t", "uint64_t", "i64vec*", "u64vec*"]),
+    ("GL_EXT_shader_explicit_arithmetic_types_float16", &["float16_t", "f16vec*", "f16mat*"]),
+];
+
+/// Checks the shader source (`sources[0]`) and its includes for Vulkan GLSL mistakes shaderc accepts.
+pub fn run_lints(sources: &[&str], glsl_type: shaderc::ShaderKind) -> Vec<LintFinding> {
+    let tokenized: Vec<Vec<GlslToken>> = sources.iter().map(|source| tokenize(source)).collect();
+    let mut findings = vec![];
+
+    let writeonly_images = writeonly_images(&tokenized);
+
+    for (source_index, tokens) in tokenized.iter().enumerate() {
+        let code: Vec<GlslToken> = tokens.iter().copied().filter(|t| t.kind != TokenKind::Comment).collect();
+        let (declarations, directives) = split_global_scope(&code);
+
+        for declaration in declarations.iter() {
+            lint_declaration(declaration, source_index, &mut findings);
+        }
+
+        for directive in directives.iter() {
+            lint_extension(directive, &tokenized, source_index, &mut findings);
+        }
+
+        for (i, token) in code.iter().enumerate() {
+            let reads_image = token.kind == TokenKind::Ident
+                && (token.text == "imageLoad" || token.text.starts_with("imageAtomic"));
+            if !reads_image || !code.get(i + 1).is_some_and(|t| t.is("(")) {
+                continue;
+            }
+
+            if let Some(image) = code.get(i + 2).filter(|t| writeonly_images.contains(&t.text)) {
+                findings.push(LintFinding {
+                    lint: "read_writeonly_image",
+                    message: format!("`{}` is declared writeonly but read with `{}`", image.text, token.text),
+                    source_index,
+                    line: image.line,
+                    first_line: image.line,
+                    offset: Some(image.offset),
+                });
+            }
+        }
+    }
+
+    if glsl_type == ShaderKind::Compute {
+        let has_local_size = tokenized.iter().flatten()
+            .any(|t| t.kind == TokenKind::Ident && t.text.starts_with("local_size_"));
+        let main = tokenized[0].windows(2)
+            .find(|w| w[0].is("main") && w[1].is("("))
+            .map(|w| w[0]);
+
+        if !has_local_size {
+            findings.push(LintFinding {
+                lint: "missing_local_size",
+                message: "compute shader has no layout(local_size_x = ...) in; the workgroup size defaults to 1x1x1".to_string(),
+                source_index: 0,
+                line: main.map(|t| t.line).unwrap_or(0),
+                first_line: main.map(|t| t.line).unwrap_or(0),
+                offset: main.map(|t| t.offset),
+            });
+        }
+    }
+
+    findings.retain(|finding| !is_allowed(&tokenized[finding.source_index], finding));
+    findings
+}
+
+/// Expands to a `#[deprecated]` constant used at `span`, which is the only way to emit a warning on stable.
+pub fn warning_tokens(span: Span, lint: &str, message: &str) -> TokenStream {
+    let note = format!("glsl lint `{lint}`: {message}");
+    let ident = Ident::new(lint, span);
+
+    quote! {{
+        #[deprecated(note = #note)]
+        #[allow(non_upper_case_globals)]
+        const #ident: () = ();
+        let _ = #ident;
+    }}
+}
+
+fn is_allowed(tokens: &[GlslToken], finding: &LintFinding) -> bool {
+    tokens.iter().enumerate()
+        .filter(|(_, t)| t.kind == TokenKind::Comment)
+        .filter(|(i, t)| {
+            let own_line = *i == 0 || tokens[i - 1].line != t.line;
+            t.line == finding.line || own_line && (t.line + 1 == finding.line || t.line + 1 == finding.first_line)
+        })
+        .map(|(_, t)| t)
+        .filter_map(|t| t.text.find(ALLOW_COMMENT).map(|i| &t.text[(i + ALLOW_COMMENT.len())..]))
+        .filter_map(|allowed| allowed.find(')').map(|end| &allowed[..end]))
+        .any(|allowed| allowed.split(',').any(|lint| lint.trim() == finding.lint))
+}
+
+/// Splits tokens at global scope into declarations (ending with `;` or a function body) and preprocessor directives.
+fn split_global_scope<'a>(code: &[GlslToken<'a>]) -> (Vec<Vec<GlslToken<'a>>>, Vec<Vec<GlslToken<'a>>>) {
+    let mut declarations = vec![];
+    let mut directives: Vec<Vec<GlslToken>> = vec![];
+    let mut current = vec![];
+    let mut depth = 0;
+    let mut directive_line = None;
+
+    for (i, token) in code.iter().enumerate() {
+        if directive_line == Some(token.line) {
+            directives.last_mut().unwrap().push(*token);
+            continue;
+        }
+        directive_line = None;
+
+        let starts_line = i == 0 || code[i - 1].line != token.line;
+        if token.is("#") && starts_line {
+            directive_line = Some(token.line);
+            directives.push(vec![*token]);
+            continue;
+        }
+
+        current.push(*token);
+        if token.is("{") {
+            depth += 1;
+        } else if token.is("}") {
+            depth -= 1;
+            let body_start = current.iter().position(|t| t.is("{")).unwrap_or(0);
+            if depth == 0 && body_start > 0 && current[body_start - 1].is(")") {
+                declarations.push(std::mem::take(&mut current));
+            }
+        } else if token.is(";") && depth == 0 {
+            declarations.push(std::mem::take(&mut current));
+        }
+    }
+
+    (declarations, directives)
+}
+
+/// Identifiers inside all `layout(...)` qualifiers of a declaration.
+fn layout_qualifiers<'a>(declaration: &[GlslToken<'a>]) -> Vec<&'a str> {
+    let mut qualifiers = vec![];
+    let mut i = 0;
+    while i < declaration.len() {
+        if declaration[i].is("layout") && declaration.get(i + 1).is_some_and(|t| t.is("(")) {
+            i += 2;
+            while i < declaration.len() && !declaration[i].is(")") {
+                if declaration[i].kind == TokenKind::Ident {
+                    qualifiers.push(declaration[i].text);
+                }
+                i += 1;
+            }
+        }
+        i += 1;
+    }
+    qualifiers
+}
+
+/// The declaration without its `layout(...)` qualifiers.
+fn without_layout<'a>(declaration: &[GlslToken<'a>]) -> Vec<GlslToken<'a>> {
+    let mut tokens = vec![];
+    let mut in_layout = false;
+    for (i, token) in declaration.iter().enumerate() {
+        if token.is("layout") && declaration.get(i + 1).is_some_and(|t| t.is("(")) {
+            in_layout = true;
+        } else if in_layout && token.is(")") {
+            in_layout = false;
+        } else if !in_layout {
+            tokens.push(*token);
+        }
+    }
+    tokens
+}
+
+/// The declared name: the last identifier before any array size, initializer or the final `;`.
+fn declared_name<'a>(tokens: &[GlslToken<'a>]) -> Option<GlslToken<'a>> {
+    without_layout(tokens).into_iter()
+        .take_while(|t| !(t.is("[") || t.is("=") || t.is(";")))
+        .filter(|t| t.kind == TokenKind::Ident)
+        .last()
+        .filter(|t| !["uniform", "buffer", "in", "out"].contains(&t.text))
+}
+
+fn lint_declaration(declaration: &[GlslToken], source_index: usize, findings: &mut Vec<LintFinding>) {
+    let layout = layout_qualifiers(declaration);
+    let block_start = declaration.iter().position(|t| t.is("{"));
+    let head = &declaration[..block_start.unwrap_or(declaration.len())];
+
+    let is_uniform = head.iter().any(|t| t.is("uniform"));
+    let is_buffer = head.iter().any(|t| t.is("buffer"));
+    if !(is_uniform || is_buffer) {
+        return;
+    }
+
+    let block_end = declaration.iter().rposition(|t| t.is("}"));
+    let name = match (block_start, block_end) {
+        (Some(_), Some(end)) => declared_name(&declaration[(end + 1)..]).or_else(|| declared_name(head)),
+        _ => declared_name(head),
+    };
+    let Some(name) = name else { return };
+
+    if !layout.contains(&"binding") && !layout.contains(&"push_constant") {
+        findings.push(LintFinding {
+            lint: "missing_binding",
+            message: format!("`{}` has no explicit binding; add layout(binding = <n>)", name.text),
+            source_index,
+            line: name.line,
+            first_line: declaration[0].line,
+            offset: Some(name.offset),
+        });
+    }
+
+    let std140 = layout.contains(&"std140")
+        || is_uniform && !layout.iter().any(|q| ["std430", "scalar", "push_constant"].contains(q));
+    if let (true, Some(start), Some(end)) = (std140, block_start, block_end) {
+        for member in declaration[(start + 1)..end].split(|t| t.is(";")) {
+            if !member.iter().any(|t| VEC3_TYPES.contains(&t.text)) {
+                continue;
+            }
+            if let Some(member_name) = declared_name(member) {
+                findings.push(LintFinding {
+                    lint: "std140_vec3",
+                    message: format!("`{}` is a vec3 in a std140 block; it is aligned to 16 bytes, \
+                        use a vec4 or add explicit padding", member_name.text),
+                    source_index,
+                    line: member_name.line,
+                    first_line: member[0].line,
+                    offset: Some(member_name.offset),
+                });
+            }
+        }
+    }
+}
+
+fn lint_extension(directive: &[GlslToken], tokenized: &[Vec<GlslToken>], source_index: usize, findings: &mut Vec<LintFinding>) {
+    if directive.len() < 5 || !directive[1].is("extension") || directive[4].is("disable") {
+        return;
+    }
+    let extension = directive[2];
+
+    let Some((_, identifiers)) = EXTENSION_IDENTIFIERS.iter().find(|(name, _)| *name == extension.text) else {
+        return;
+    };
+
+    let used = tokenized.iter().flatten()
+        .filter(|t| t.kind == TokenKind::Ident && t.offset != extension.offset)
+        .any(|t| identifiers.iter().any(|identifier| matches_identifier(identifier, t.text)));
+
+    if !used {
+        findings.push(LintFinding {
+            lint: "unused_extension",
+            message: format!("extension `{}` is enabled but never used", extension.text),
+            source_index,
+            line: extension.line,
+            first_line: extension.line,
+            offset: Some(extension.offset),
+        });
+    }
+}
+
+fn matches_identifier(pattern: &str, ident: &str) -> bool {
+    match pattern.split_once('*') {
+        Some((prefix, suffix)) => ident.starts_with(prefix) && ident.ends_with(suffix) && ident.len() > prefix.len(),
+        None => pattern == ident,
+    }
+}
+
+/// `image2D`, `iimageCube`, `uimage2DMSArray`, ...: the opaque image types, not identifiers like `imageData`.
+fn is_image_type(ident: &str) -> bool {
+    ["image", "iimage", "uimage"].iter()
+        .filter_map(|prefix| ident.strip_prefix(prefix))
+        .any(|dimension| ["1D", "2D", "3D", "Cube", "Rect", "Buffer"].iter().any(|d| dimension.starts_with(d)))
+}
+
+fn writeonly_images<'a>(tokenized: &[Vec<GlslToken<'a>>]) -> Vec<&'a str> {
+    let mut images = vec![];
+    for tokens in tokenized {
+        let code: Vec<GlslToken> = tokens.iter().copied().filter(|t| t.kind != TokenKind::Comment).collect();
+        let (declarations, _) = split_global_scope(&code);
+        for declaration in declarations {
+            let writeonly = declaration.iter().any(|t| t.is("writeonly")) && declaration.iter().any(|t| t.is("uniform"));
+            let image = declaration.iter().any(|t| t.kind == TokenKind::Ident && is_image_type(t.text));
+            if writeonly && image {
+                if let Some(name) = declared_name(&declaration) {
+                    images.push(name.text);
+                }
+            }
+        }
+    }
+    images
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lints(sources: &[&str], glsl_type: ShaderKind) -> Vec<(&'static str, usize, usize)> {
+        run_lints(sources, glsl_type).into_iter().map(|f| (f.lint, f.source_index, f.line)).collect()
+    }
+
+    #[test]
+    fn findings_in_includes() {
+        let shader = "#version 450 core\nlayout(local_size_x = 8) in;\nvoid main () {\n    imageLoad(img, ivec2(0));\n}\n";
+        let include = "layout(binding = 0, rgba8) uniform writeonly image2D img;\n#extension GL_EXT_ray_query : enable\n";
+        assert_eq!(lints(&[shader, include], ShaderKind::Compute), [
+            ("read_writeonly_image", 0, 3),
+            ("unused_extension", 1, 1),
+        ]);
+    }
+
+    #[test]
+    fn writeonly_buffers_are_not_images() {
+        let shader = "\
+layout(local_size_x = 8) in;
+layout(binding = 0, rgba8) uniform writeonly uimage2DArray layers;
+layout(binding = 1) writeonly buffer imageInfo {
+    uint imageData[];
+} images;
+void main () {
+    imageLoad(images, ivec3(0));
+    imageAtomicAdd(layers, ivec3(0), 1u);
+}
+";
+        assert_eq!(lints(&[shader], ShaderKind::Compute), [("read_writeonly_image", 0, 7)]);
+    }
+
+    #[test]
+    fn local_size_only_for_compute() {
+        let shader = "#version 450 core\nvoid main () {\n}\n";
+        assert_eq!(lints(&[shader], ShaderKind::Compute), [("missing_local_size", 0, 1)]);
+        assert!(lints(&[shader], ShaderKind::Fragment).is_empty());
+    }
+
+    #[test]
+    fn allow_comments() {
+        let shader = "\
+// glsl-lint: allow(missing_binding)
+uniform Params {
+    vec3 color; // glsl-lint: allow(std140_vec3, missing_binding)
+} params;
+uniform Other {
+    // glsl-lint: allow(std140_vec3)
+    vec3 offset;
+    vec3 scale;
+} other;
+";
+        assert_eq!(lints(&[shader], ShaderKind::Fragment), [
+            ("missing_binding", 0, 8),
+            ("std140_vec3", 0, 7),
+        ]);
+    }
+
+    #[test]
+    fn warnings_are_deprecated_constants() {
+        let tokens = warning_tokens(Span::call_site(), "std140_vec3", "`color` is a vec3").to_string();
+        assert!(tokens.contains("# [deprecated (note = \"glsl lint `std140_vec3`: `color` is a vec3\")]"), "{tokens}");
+        assert!(tokens.contains("let _ = std140_vec3 ;"), "{tokens}");
+    }
+}
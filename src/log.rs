@@ -0,0 +1,72 @@
+use std::env;
+use std::fmt::Display;
+
+/// Environment variable that turns on build logging: `GLSL_COMPILER_LOG=info|debug`.
+pub const LOG_ENV: &str = "GLSL_COMPILER_LOG";
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum LogLevel {
+    Off,
+    Info,
+    Debug,
+}
+
+impl LogLevel {
+    pub fn from_env() -> LogLevel {
+        match env::var(LOG_ENV) {
+            Ok(value) => match value.trim().to_lowercase().as_str() {
+                "info" => LogLevel::Info,
+                "debug" => LogLevel::Debug,
+                _ => LogLevel::Off,
+            },
+            Err(_) => LogLevel::Off,
+        }
+    }
+}
+
+/// Writes build logs of one `glsl!` invocation to stderr.
+/// Silent unless `GLSL_COMPILER_LOG` is set or the marco has the `print` key.
+pub struct Logger {
+    level: LogLevel,
+    shader: String,
+}
+
+impl Logger {
+    pub fn new(print: bool) -> Logger {
+        let level = LogLevel::from_env();
+        let level = if print { LogLevel::Debug } else { level };
+
+        Logger {
+            level,
+            shader: String::new(),
+        }
+    }
+
+    pub fn set_shader(&mut self, shader: &str) {
+        self.shader = shader.to_string();
+    }
+
+    pub fn enabled(&self, level: LogLevel) -> bool {
+        level != LogLevel::Off && level <= self.level
+    }
+
+    pub fn info(&self, message: impl Display) {
+        self.log(LogLevel::Info, message)
+    }
+
+    pub fn debug(&self, message: impl Display) {
+        self.log(LogLevel::Debug, message)
+    }
+
+    fn log(&self, level: LogLevel, message: impl Display) {
+        if !self.enabled(level) {
+            return;
+        }
+
+        let level = match level {
+            LogLevel::Debug => "debug",
+            _ => "info",
+        };
+        eprintln!("[glsl_compiler {level}] {}: {message}", self.shader);
+    }
+}
@@ -0,0 +1,296 @@
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use crate::tokenizer::{tokenize, GlslToken, TokenKind};
+
+/// A line of source after joining line continuations, with comments replaced by spaces.
+pub struct LogicalLine {
+    /// Byte range of the line in the source, without the trailing new line.
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+/// Splits `source` into logical lines the way the GLSL preprocessor sees them.
+pub fn logical_lines(source: &str) -> Vec<LogicalLine> {
+    let code = blank_comments(source);
+
+    let mut lines = vec![];
+    let mut start = 0;
+    let mut text = String::new();
+    let mut offset = 0;
+    while offset < code.len() {
+        let line_end = code[offset..].find('\n').map(|i| i + offset).unwrap_or(code.len());
+        let line = code[offset..line_end].strip_suffix('\r').unwrap_or(&code[offset..line_end]);
+
+        if let Some(continued) = line.strip_suffix('\\') {
+            text.push_str(continued);
+        } else {
+            text.push_str(line);
+            lines.push(LogicalLine { range: start..line_end, text: std::mem::take(&mut text) });
+            start = line_end + 1;
+        }
+        offset = line_end + 1;
+    }
+    if start < code.len() {
+        lines.push(LogicalLine { range: start..code.len(), text });
+    }
+
+    lines
+}
+
+/// Replaces every comment with spaces, keeping the new lines so byte offsets stay the same.
+fn blank_comments(source: &str) -> String {
+    let mut code = source.to_string();
+    for token in tokenize(source).iter().filter(|t| t.kind == TokenKind::Comment) {
+        let blank: String = token.text.chars()
+            .map(|c| if c == '\n' { "\n".to_string() } else { " ".repeat(c.len_utf8()) })
+            .collect();
+        code.replace_range(token.offset..(token.offset + token.text.len()), &blank);
+    }
+    code
+}
+
+/// The name and the rest of a `# <name> <rest>` directive line.
+pub fn split_directive(line: &str) -> Option<(&str, &str)> {
+    let directive = line.trim_start().strip_prefix('#')?.trim_start();
+    let name_end = directive.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(directive.len());
+    Some((&directive[..name_end], directive[name_end..].trim()))
+}
+
+#[derive(Clone)]
+struct Conditional {
+    /// Whether the enclosing region is active.
+    parent_active: bool,
+    /// Whether one branch of this conditional was already taken.
+    taken: bool,
+    active: bool,
+}
+
+/// Tracks macros and conditional compilation, so `#include`s are only expanded where shaderc would expand them.
+#[derive(Clone)]
+pub struct Preprocessor {
+    defines: HashMap<String, String>,
+    function_macros: HashSet<String>,
+    conditionals: Vec<Conditional>,
+}
+
+impl Preprocessor {
+    pub fn new() -> Preprocessor {
+        let mut defines = HashMap::new();
+        for (name, value) in [("GL_core_profile", "1"), ("VULKAN", "100"), ("GL_SPIRV", "100"), ("GL_GOOGLE_include_directive", "1")] {
+            defines.insert(name.to_string(), value.to_string());
+        }
+
+        Preprocessor {
+            defines,
+            function_macros: HashSet::new(),
+            conditionals: vec![],
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.conditionals.last().map(|c| c.active).unwrap_or(true)
+    }
+
+    /// Applies a directive other than `#include`.
+    pub fn directive(&mut self, name: &str, rest: &str) {
+        let active = self.is_active();
+        match name {
+            "if" | "ifdef" | "ifndef" => {
+                let condition = active && match name {
+                    "if" => self.evaluate(rest),
+                    "ifdef" => self.is_defined(rest),
+                    _ => !self.is_defined(rest),
+                };
+                self.conditionals.push(Conditional { parent_active: active, taken: condition, active: condition });
+            }
+            "elif" => {
+                let taken = self.conditionals.last().map(|c| !c.parent_active || c.taken).unwrap_or(true);
+                let condition = !taken && self.evaluate(rest);
+                if let Some(conditional) = self.conditionals.last_mut() {
+                    conditional.active = condition;
+                    conditional.taken |= condition;
+                }
+            }
+            "else" => {
+                if let Some(conditional) = self.conditionals.last_mut() {
+                    conditional.active = conditional.parent_active && !conditional.taken;
+                    conditional.taken = true;
+                }
+            }
+            "endif" => {
+                self.conditionals.pop();
+            }
+            "define" if active => {
+                let name_end = rest.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(rest.len());
+                let (macro_name, value) = rest.split_at(name_end);
+                if value.starts_with('(') {
+                    self.function_macros.insert(macro_name.to_string());
+                } else {
+                    self.defines.insert(macro_name.to_string(), value.trim().to_string());
+                }
+            }
+            "undef" if active => {
+                self.defines.remove(rest);
+                self.function_macros.remove(rest);
+            }
+            "version" if active => {
+                let version = rest.split_whitespace().next().unwrap_or_default();
+                self.defines.insert("__VERSION__".to_string(), version.to_string());
+            }
+            _ => {}
+        }
+    }
+
+    fn is_defined(&self, name: &str) -> bool {
+        let name = name.split_whitespace().next().unwrap_or_default();
+        self.defines.contains_key(name) || self.function_macros.contains(name)
+    }
+
+    /// Evaluates an `#if` expression. Expressions it can't evaluate count as true, so their includes are still expanded.
+    fn evaluate(&self, expression: &str) -> bool {
+        let tokens = self.expand_macros(expression, 0);
+        if tokens.is_none() {
+            return true
+        }
+        let tokens = tokens.unwrap();
+
+        let mut parser = ExpressionParser { tokens: &tokens, position: 0 };
+        let value = parser.ternary();
+        if value.is_none() || parser.position != tokens.len() {
+            return true
+        }
+        value.unwrap() != 0
+    }
+
+    /// Replaces `defined` and object-like macros with their values and unknown identifiers with 0.
+    fn expand_macros(&self, expression: &str, depth: usize) -> Option<Vec<String>> {
+        if depth > 32 {
+            return None
+        }
+
+        let tokens: Vec<GlslToken> = tokenize(expression).into_iter().filter(|t| t.kind != TokenKind::Comment).collect();
+        let mut expanded = vec![];
+        let mut i = 0;
+        while i < tokens.len() {
+            let token = tokens[i];
+            if token.is("defined") {
+                let parenthesized = tokens.get(i + 1).is_some_and(|t| t.is("("));
+                let name = tokens.get(if parenthesized { i + 2 } else { i + 1 })?;
+                let defined = self.is_defined(name.text);
+                expanded.push(if defined { "1" } else { "0" }.to_string());
+                i += if parenthesized { 4 } else { 2 };
+                continue;
+            }
+
+            if token.kind == TokenKind::Ident {
+                if self.function_macros.contains(token.text) {
+                    return None
+                }
+                match self.defines.get(token.text) {
+                    Some(value) => expanded.extend(self.expand_macros(value, depth + 1)?),
+                    None => expanded.push("0".to_string()),
+                }
+            } else {
+                expanded.push(token.text.to_string());
+            }
+            i += 1;
+        }
+        Some(expanded)
+    }
+}
+
+/// Precedence climbing parser for integer `#if` expressions.
+struct ExpressionParser<'a> {
+    tokens: &'a [String],
+    position: usize,
+}
+
+const BINARY_OPERATORS: &[&[&str]] = &[
+    &["||"], &["&&"], &["|"], &["^"], &["&"], &["==", "!="], &["<", ">", "<=", ">="], &["<<", ">>"], &["+", "-"], &["*", "/", "%"],
+];
+
+impl<'a> ExpressionParser<'a> {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.position).map(String::as_str)
+    }
+
+    fn ternary(&mut self) -> Option<i64> {
+        let condition = self.binary(0)?;
+        if self.peek() != Some("?") {
+            return Some(condition)
+        }
+        self.position += 1;
+        let if_true = self.ternary()?;
+        if self.peek() != Some(":") {
+            return None
+        }
+        self.position += 1;
+        let if_false = self.ternary()?;
+        Some(if condition != 0 { if_true } else { if_false })
+    }
+
+    fn binary(&mut self, level: usize) -> Option<i64> {
+        if level == BINARY_OPERATORS.len() {
+            return self.unary()
+        }
+
+        let mut left = self.binary(level + 1)?;
+        while let Some(operator) = self.peek().filter(|op| BINARY_OPERATORS[level].contains(op)) {
+            let operator = operator.to_string();
+            self.position += 1;
+            let right = self.binary(level + 1)?;
+            left = match operator.as_str() {
+                "||" => (left != 0 || right != 0) as i64,
+                "&&" => (left != 0 && right != 0) as i64,
+                "|" => left | right,
+                "^" => left ^ right,
+                "&" => left & right,
+                "==" => (left == right) as i64,
+                "!=" => (left != right) as i64,
+                "<" => (left < right) as i64,
+                ">" => (left > right) as i64,
+                "<=" => (left <= right) as i64,
+                ">=" => (left >= right) as i64,
+                "<<" => left.checked_shl(right as u32)?,
+                ">>" => left.checked_shr(right as u32)?,
+                "+" => left.wrapping_add(right),
+                "-" => left.wrapping_sub(right),
+                "*" => left.wrapping_mul(right),
+                "/" => left.checked_div(right)?,
+                _ => left.checked_rem(right)?,
+            };
+        }
+        Some(left)
+    }
+
+    fn unary(&mut self) -> Option<i64> {
+        let token = self.peek()?.to_string();
+        self.position += 1;
+        match token.as_str() {
+            "!" => Some((self.unary()? == 0) as i64),
+            "~" => Some(!self.unary()?),
+            "-" => Some(self.unary()?.wrapping_neg()),
+            "+" => self.unary(),
+            "(" => {
+                let value = self.ternary()?;
+                if self.peek() != Some(")") {
+                    return None
+                }
+                self.position += 1;
+                Some(value)
+            }
+            number => parse_integer(number),
+        }
+    }
+}
+
+fn parse_integer(text: &str) -> Option<i64> {
+    let text = text.trim_end_matches(['u', 'U']);
+    if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        i64::from_str_radix(hex, 16).ok()
+    } else if text.len() > 1 && text.starts_with('0') {
+        i64::from_str_radix(&text[1..], 8).ok()
+    } else {
+        text.parse().ok()
+    }
+}
@@ -1,7 +1,265 @@
+use std::ops::Range;
 use proc_macro_error::abort_call_site;
-use crate::find_closing_bracket;
+use crate::config::string_list;
+use crate::tokenizer::{tokenize, GlslToken, TokenKind};
 
+/// Where the injected profiler buffers are bound: `profile = { set = 3, in_binding = 0, out_binding = 1 }`.
+/// With `push_constant` `ProfilerIn` is a push constant block instead of a uniform buffer.
+/// With `aggregate` every invocation is measured, `subgroup` reduces the measurements per subgroup first.
+/// With `functions` every function gets a scope, `functions = ["shade*", "!noise_*"]` selects them by name.
+/// With `printf` the `printf` calls of the profiled invocation are written into a buffer at `printf_binding`.
+pub struct ProfileOptions {
+    pub set: u32,
+    pub in_binding: u32,
+    pub out_binding: u32,
+    pub push_constant: bool,
+    pub aggregate: bool,
+    pub subgroup: bool,
+    pub functions: Option<Vec<String>>,
+    pub printf: bool,
+    pub printf_binding: u32,
+}
+
+impl Default for ProfileOptions {
+    fn default() -> Self {
+        ProfileOptions {
+            set: 0,
+            in_binding: 10,
+            out_binding: 11,
+            push_constant: false,
+            aggregate: false,
+            subgroup: false,
+            functions: None,
+            printf: false,
+            printf_binding: 12,
+        }
+    }
+}
+
+impl ProfileOptions {
+    /// Parses the value of `profile = <value>`.
+    pub fn parse(text: &str) -> ProfileOptions {
+        let write_help = "Write: profile = { set = <n>, in_binding = <n>, out_binding = <n>, push_constant, functions = [\"<pattern>\", ...], printf, printf_binding = <n> } or profile = { aggregate, subgroup, set = <n>, out_binding = <n> }";
+        match text {
+            "aggregate" => return ProfileOptions { aggregate: true, ..ProfileOptions::default() },
+            "functions" => return ProfileOptions { functions: Some(vec![]), ..ProfileOptions::default() },
+            "printf" => return ProfileOptions { printf: true, ..ProfileOptions::default() },
+            _ => {}
+        }
+        let inner = text.strip_prefix('{').and_then(|text| text.strip_suffix('}'));
+        if inner.is_none() {
+            abort_call_site!("Invalid profile Value: {}", text; help=write_help)
+        }
+
+        let mut options = ProfileOptions::default();
+        for entry in split_entries(inner.unwrap()) {
+            match entry {
+                "push_constant" => { options.push_constant = true; continue }
+                "aggregate" => { options.aggregate = true; continue }
+                "subgroup" => { options.subgroup = true; continue }
+                "functions" => { options.functions = Some(vec![]); continue }
+                "printf" => { options.printf = true; continue }
+                _ => {}
+            }
+            if let Some(("functions", patterns)) = entry.split_once('=').map(|(key, value)| (key.trim(), value)) {
+                options.functions = Some(string_list(patterns));
+                continue
+            }
+
+            let (key, value) = entry.split_once('=').unwrap_or((entry, ""));
+            let value = value.trim().parse::<u32>();
+            if value.is_err() {
+                abort_call_site!("Invalid profile option: {}", entry; help=write_help)
+            }
+            let value = value.unwrap();
+
+            match key.trim() {
+                "set" => options.set = value,
+                "in_binding" => options.in_binding = value,
+                "out_binding" => options.out_binding = value,
+                "printf_binding" => options.printf_binding = value,
+                _ => abort_call_site!("Invalid profile option: {}", entry; help=write_help),
+            }
+        }
+
+        if options.subgroup && !options.aggregate {
+            abort_call_site!("subgroup only works with aggregate profiling"; help=write_help)
+        }
+        if options.push_constant && options.aggregate {
+            abort_call_site!("aggregate profiling doesn't use ProfilerIn, push_constant has no effect"; help=write_help)
+        }
+        if options.printf && options.aggregate {
+            abort_call_site!("printf only works for the single invocation selected by ProfilerIn, not with aggregate"; help=write_help)
+        }
+        options
+    }
 
+    fn profiler_in_layout(&self) -> String {
+        if self.push_constant {
+            "layout(push_constant)".to_string()
+        } else {
+            format!("layout(set = {}, binding = {})", self.set, self.in_binding)
+        }
+    }
+}
+
+/// Splits `a, b = ["c", "d"]` at the commas outside of brackets and strings.
+fn split_entries(text: &str) -> Vec<&str> {
+    let mut entries = vec![];
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut entry_start = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            '[' | '{' | '(' if !in_string => depth += 1,
+            ']' | '}' | ')' if !in_string => depth -= 1,
+            ',' if !in_string && depth == 0 => {
+                entries.push(text[entry_start..i].trim());
+                entry_start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    entries.push(text[entry_start..].trim());
+    entries.into_iter().filter(|entry| !entry.is_empty()).collect()
+}
+
+/// Matches `*` and `?` wildcards.
+fn glob_match(pattern: &str, name: &str) -> bool {
+    match pattern.chars().next() {
+        None => name.is_empty(),
+        Some('*') => (0..=name.len()).filter(|i| name.is_char_boundary(*i)).any(|i| glob_match(&pattern[1..], &name[i..])),
+        Some('?') => name.chars().next().is_some_and(|c| glob_match(&pattern[1..], &name[c.len_utf8()..])),
+        Some(c) => name.starts_with(c) && glob_match(&pattern[c.len_utf8()..], &name[c.len_utf8()..]),
+    }
+}
+
+/// Whether `profile = { functions = [...] }` selects the function `name`. Patterns starting with `!` exclude functions,
+/// without other patterns every function is selected. `main` is always profiled.
+fn is_profiled_function(patterns: &[String], name: &str) -> bool {
+    if name == "main" {
+        return true
+    }
+    let (excludes, includes): (Vec<&String>, Vec<&String>) = patterns.iter().partition(|pattern| pattern.starts_with('!'));
+    let included = includes.is_empty() || includes.iter().any(|pattern| glob_match(pattern, name));
+    included && !excludes.iter().any(|pattern| glob_match(&pattern[1..], name))
+}
+
+/// Places a `PROFILE("<function name>");` at the start of every function selected by `patterns`
+/// that doesn't already start with a profile scope. Overloads are named with their parameter types,
+/// `PROFILE("to_linear(vec4)");`, so an overload calling another one doesn't reuse its open scope.
+fn insert_function_markers(source: &str, patterns: &[String]) -> String {
+    let tokens = tokenize(source);
+    let code: Vec<GlslToken> = tokens.iter().copied().filter(|token| token.kind != TokenKind::Comment).collect();
+    let is_marker = |token: &GlslToken| token.is("PROFILE") || token.text.starts_with("//PROFILE(\"");
+
+    // (name, parameter list, body start) of the selected definitions.
+    let mut definitions = vec![];
+    let mut depth = 0;
+    for (i, token) in code.iter().enumerate() {
+        if token.is("{") {
+            depth += 1;
+        } else if token.is("}") {
+            depth -= 1;
+        }
+
+        // <type> <name>(<parameters>) {
+        let is_definition = depth == 0
+            && i > 0 && (code[i - 1].kind == TokenKind::Ident || code[i - 1].is("]"))
+            && !(i > 1 && code[i - 2].is("#"))
+            && token.kind == TokenKind::Ident
+            && code.get(i + 1).is_some_and(|t| t.is("("));
+        if !is_definition {
+            continue
+        }
+        let parameters_end = matching_bracket(&code, i + 1);
+        let body_start = parameters_end + 1;
+        if !code.get(body_start).is_some_and(|t| t.is("{")) || !is_profiled_function(patterns, token.text) {
+            continue
+        }
+        definitions.push((token.text, &code[(i + 2)..parameters_end], body_start));
+    }
+
+    let mut insertions = vec![];
+    for (name, parameters, body_start) in definitions.iter() {
+        let body_offset = code[*body_start].offset + 1;
+        let first_token = tokens.iter().find(|t| t.offset >= body_offset);
+        if first_token.is_some_and(is_marker) {
+            continue
+        }
+
+        let overloaded = definitions.iter().filter(|(other, _, _)| other == name).count() > 1;
+        let scope_name = if overloaded {
+            format!("{name}({})", parameter_types(parameters).join(", "))
+        } else {
+            name.to_string()
+        };
+        insertions.push((body_offset, format!(" PROFILE(\"{scope_name}\");")));
+    }
+
+    let mut source = source.to_string();
+    for (offset, marker) in insertions.into_iter().rev() {
+        source.insert_str(offset, &marker);
+    }
+    source
+}
+
+/// The type of every parameter in a parameter list, without qualifiers and names: `vec4`, `uint` for `inout uint seed`.
+fn parameter_types<'a>(parameters: &[GlslToken<'a>]) -> Vec<&'a str> {
+    const QUALIFIERS: &[&str] = &["const", "in", "out", "inout", "highp", "mediump", "lowp", "precise",
+        "coherent", "volatile", "restrict", "readonly", "writeonly"];
+
+    parameters.split(|t| t.is(","))
+        .filter_map(|parameter| parameter.iter().find(|t| t.kind == TokenKind::Ident && !QUALIFIERS.contains(&t.text)))
+        .map(|t| t.text)
+        .filter(|t| *t != "void")
+        .collect()
+}
+
+/// What the host needs to read the profiler buffers.
+pub struct ProfileMetadata {
+    /// The scope and counter names by id.
+    pub names: Vec<String>,
+    /// The enclosing scope of every id.
+    pub parents: Vec<Option<usize>>,
+    /// The ids of the counters.
+    pub counters: Vec<usize>,
+    pub printf_formats: Vec<String>,
+    /// `uint`s per printf record.
+    pub printf_record: usize,
+}
+
+impl ProfileMetadata {
+    /// The `glsl_compiler_runtime::ProfileInfo` the marco returns next to the binary.
+    pub fn info_code(&self, options: &ProfileOptions) -> String {
+        let names: String = self.names.iter().map(|name| format!("{name:?},")).collect();
+        let parents: String = self.parents.iter()
+            .map(|parent| match parent {
+                Some(parent) => format!("Some({parent}),"),
+                None => "None,".to_string(),
+            })
+            .collect();
+        let counters: String = self.counters.iter().map(|id| format!("{id},")).collect();
+
+        let in_binding = if options.aggregate || options.push_constant {
+            "None".to_string()
+        } else {
+            format!("Some({})", options.in_binding)
+        };
+        let printf = if options.printf {
+            let formats: String = self.printf_formats.iter().map(|format| format!("{format:?},")).collect();
+            format!("Some(::glsl_compiler_runtime::PrintfInfo {{ binding: {}, record: {}, formats: &[{formats}] }})",
+                options.printf_binding, self.printf_record)
+        } else {
+            "None".to_string()
+        };
+
+        format!("::glsl_compiler_runtime::ProfileInfo {{ names: &[{names}], parents: &[{parents}], counters: &[{counters}], aggregate: {}, \
+            layout: ::glsl_compiler_runtime::ProfileLayout {{ set: {}, in_binding: {in_binding}, push_constant: {}, out_binding: {} }}, printf: {printf} }}",
+            options.aggregate, options.set, options.push_constant && !options.aggregate, options.out_binding)
+    }
+}
 
 fn get_id(name: &str, profile_scope_names: &mut Vec<String>) -> usize {
     let id = profile_scope_names.iter().position(|t| t == name);
@@ -19,113 +277,699 @@ fn profile_begin_code(name: &str, profile_scope_names: &mut Vec<String>, with_in
     let id = get_id(name, profile_scope_names);
 
     let possible_init = if with_init {
-        "PROFILE_INIT();\n"
+        "PROFILE_INIT(); "
     } else {""};
 
-    format!("{possible_init}PROFILE_SCOPE_BEING({id});\n")
+    format!("{possible_init}PROFILE_SCOPE_BEING({id});")
 }
 
 fn profile_end_code(name: &str, profile_scope_names: &mut Vec<String>) -> String {
     let id = get_id(name, profile_scope_names);
 
-    format!("\nPROFILE_SCOPE_END({id});\n")
+    format!("PROFILE_SCOPE_END({id}); ")
+}
+
+/// The extension a stage needs for its invocation IDs and the expression selecting the profiled invocation.
+fn invocation_selector(glsl_type: shaderc::ShaderKind) -> (&'static str, &'static str) {
+    match glsl_type {
+        shaderc::ShaderKind::Fragment => ("", "uvec2(gl_FragCoord.xy)"),
+        shaderc::ShaderKind::Vertex => ("", "uvec2(gl_VertexIndex, gl_InstanceIndex)"),
+        shaderc::ShaderKind::Geometry => ("", "uvec2(gl_PrimitiveIDIn, gl_InvocationID)"),
+        shaderc::ShaderKind::Mesh | shaderc::ShaderKind::Task => {
+            ("#extension GL_EXT_mesh_shader : require", "uvec2(gl_WorkGroupID.x, gl_LocalInvocationIndex)")
+        }
+        shaderc::ShaderKind::RayGeneration | shaderc::ShaderKind::AnyHit | shaderc::ShaderKind::ClosestHit
+        | shaderc::ShaderKind::Miss | shaderc::ShaderKind::Intersection | shaderc::ShaderKind::Callable => {
+            ("#extension GL_EXT_ray_tracing : require", "gl_LaunchIDEXT.xy")
+        }
+        _ => ("", "gl_GlobalInvocationID.xy"),
+    }
 }
 
-fn profile_inject_code<'a>(num_scopes: usize) -> String {
+fn profile_inject_code<'a>(num_scopes: usize, options: &ProfileOptions, glsl_type: shaderc::ShaderKind) -> String {
+    if options.aggregate {
+        return profile_aggregate_inject_code(num_scopes, options)
+    }
+
+    let (stage_extension, selector) = invocation_selector(glsl_type);
+    let profiler_in_layout = options.profiler_in_layout();
+    let profiler_out_layout = format!("layout(set = {}, binding = {})", options.set, options.out_binding);
     format!(r#"
 #extension GL_EXT_shader_realtime_clock : require
+{stage_extension}
 
-layout(binding = 10) uniform ProfilerIn {{
+{profiler_in_layout} uniform ProfilerIn {{
     uint active_pixel_x;
     uint active_pixel_y;
 }} profiler_in;
 
-layout(binding = 11) buffer ProfilerOut {{
+{profiler_out_layout} buffer ProfilerOut {{
     uint[] data;
 }} profiler_out;
 
+bool PROFILE_ACTIVE() {{
+    return {selector} == uvec2(profiler_in.active_pixel_x, profiler_in.active_pixel_y);
+}}
+
 void PROFILE_INIT() {{
-    if (profiler_in.active_pixel_x != gl_GlobalInvocationID.x || profiler_in.active_pixel_y != gl_GlobalInvocationID.y) {{
+    if (!PROFILE_ACTIVE()) {{
         return;
     }}
 
     for (uint i = 0; i < {num_scopes}; i++) {{
         profiler_out.data[i * 5] = 0;
+        profiler_out.data[i * 5 + 1] = 0;
+        profiler_out.data[i * 5 + 2] = 0;
     }}
 }}
 
 void PROFILE_SCOPE_BEING(uint id) {{
-    if (profiler_in.active_pixel_x != gl_GlobalInvocationID.x || profiler_in.active_pixel_y != gl_GlobalInvocationID.y) {{
+    if (!PROFILE_ACTIVE()) {{
         return;
     }}
     uint index = id * 5;
 
     uvec2 timing = clockRealtime2x32EXT();
     profiler_out.data[index]++;
-    profiler_out.data[index + 1] = timing.x;
-    profiler_out.data[index + 2] = timing.y;
+    profiler_out.data[index + 3] = timing.x;
+    profiler_out.data[index + 4] = timing.y;
 }}
 
 void PROFILE_SCOPE_END(uint id) {{
-    if (profiler_in.active_pixel_x != gl_GlobalInvocationID.x || profiler_in.active_pixel_y != gl_GlobalInvocationID.y) {{
+    if (!PROFILE_ACTIVE()) {{
         return;
     }}
     uint index = id * 5;
 
     uvec2 timing = clockRealtime2x32EXT();
-    profiler_out.data[index + 3] = timing.x;
-    profiler_out.data[index + 4] = timing.y;
+    uint start_low = profiler_out.data[index + 3];
+    uint start_high = profiler_out.data[index + 4];
+    uint duration_low = timing.x - start_low;
+    uint duration_high = timing.y - start_high - (timing.x < start_low ? 1u : 0u);
+
+    uint total_low = profiler_out.data[index + 1] + duration_low;
+    profiler_out.data[index + 1] = total_low;
+    profiler_out.data[index + 2] += duration_high + (total_low < duration_low ? 1u : 0u);
+}}
+
+void PROFILE_COUNT(uint id, uint value) {{
+    if (!PROFILE_ACTIVE()) {{
+        return;
+    }}
+    uint index = id * 5;
+
+    profiler_out.data[index]++;
+    uint sum_low = profiler_out.data[index + 1] + value;
+    profiler_out.data[index + 1] = sum_low;
+    profiler_out.data[index + 2] += sum_low < value ? 1u : 0u;
+}}
+    "#)
+}
+
+/// Measures every invocation. Per scope the buffer holds the count, the total ticks as low and high word,
+/// the minimum stored as `~ticks` so that it can use `atomicMax` as well, and the maximum.
+/// Counters hold how often they were reached and the sum of their values as low and high word.
+/// The host has to clear the buffer to 0 before each dispatch.
+fn profile_aggregate_inject_code(num_scopes: usize, options: &ProfileOptions) -> String {
+    let profiler_out_layout = format!("layout(set = {}, binding = {})", options.set, options.out_binding);
+
+    let (subgroup_extensions, accumulate) = if options.subgroup {
+        ("#extension GL_KHR_shader_subgroup_basic : require\n#extension GL_KHR_shader_subgroup_arithmetic : require", r#"
+    // The 16-bit halves can't overflow when summed over a subgroup.
+    uint count = subgroupAdd(1u);
+    uint sum_low = subgroupAdd(duration & 0xFFFFu);
+    uint sum_high = subgroupAdd(duration >> 16);
+    uint min_duration = subgroupMin(duration);
+    uint max_duration = subgroupMax(duration);
+    if (!subgroupElect()) {
+        return;
+    }
+
+    uint sum = sum_low + (sum_high << 16);
+    uint carry = (sum_high >> 16) + (sum < sum_low ? 1u : 0u);
+    atomicAdd(profiler_out.data[index], count);
+    uint total_low = atomicAdd(profiler_out.data[index + 1], sum);
+    carry += total_low + sum < total_low ? 1u : 0u;
+    if (carry > 0) {
+        atomicAdd(profiler_out.data[index + 2], carry);
+    }
+    atomicMax(profiler_out.data[index + 3], ~min_duration);
+    atomicMax(profiler_out.data[index + 4], max_duration);"#)
+    } else {
+        ("", r#"
+    atomicAdd(profiler_out.data[index], 1u);
+    uint total_low = atomicAdd(profiler_out.data[index + 1], duration);
+    if (total_low + duration < total_low) {
+        atomicAdd(profiler_out.data[index + 2], 1u);
+    }
+    atomicMax(profiler_out.data[index + 3], ~duration);
+    atomicMax(profiler_out.data[index + 4], duration);"#)
+    };
+
+    format!(r#"
+#extension GL_EXT_shader_realtime_clock : require
+{subgroup_extensions}
+
+{profiler_out_layout} buffer ProfilerOut {{
+    uint[] data;
+}} profiler_out;
+
+uvec2 profile_starts[{num_scopes}];
+
+void PROFILE_INIT() {{
+}}
+
+void PROFILE_SCOPE_BEING(uint id) {{
+    profile_starts[id] = clockRealtime2x32EXT();
+}}
+
+void PROFILE_SCOPE_END(uint id) {{
+    uvec2 timing = clockRealtime2x32EXT();
+    uvec2 start = profile_starts[id];
+    uint duration_high = timing.y - start.y - (timing.x < start.x ? 1u : 0u);
+    uint duration = duration_high > 0 ? 0xFFFFFFFFu : timing.x - start.x;
+    uint index = id * 5;
+{accumulate}
+}}
+
+void PROFILE_COUNT(uint id, uint value) {{
+    uint index = id * 5;
+    atomicAdd(profiler_out.data[index], 1u);
+    uint sum_low = atomicAdd(profiler_out.data[index + 1], value);
+    if (sum_low + value < sum_low) {{
+        atomicAdd(profiler_out.data[index + 2], 1u);
+    }}
+}}
+    "#)
+}
+
+/// A `//PROFILE("<name>");` marker and the scope it starts.
+struct ProfileScope {
+    name: String,
+    /// Byte range of the marker in the source.
+    marker: Range<usize>,
+    /// Index of the first code token of the scope.
+    start: usize,
+    /// Index of the `}` closing the block the marker is placed in.
+    end: usize,
+}
+
+impl ProfileScope {
+    fn new(name: String, marker: Range<usize>, start: usize) -> ProfileScope {
+        ProfileScope { name, marker, start, end: start }
+    }
+}
+
+/// A `//COUNT("<name>");` or `COUNT("<name>", <value>);` marker.
+struct ProfileCounter {
+    name: String,
+    /// Byte range of the marker in the source.
+    marker: Range<usize>,
+    /// The GLSL expression added to the counter.
+    value: String,
+}
+
+/// Splits the arguments of `COUNT("<name>", <value>)` into the name and the value, which is `1u` when missing.
+fn counter_arguments(arguments: &str) -> Option<(String, String)> {
+    let arguments = arguments.trim().strip_prefix('"')?;
+    let (name, rest) = arguments.split_once('"')?;
+    let value = match rest.trim().strip_prefix(',') {
+        Some(value) => value.trim().to_string(),
+        None if rest.trim().is_empty() => "1u".to_string(),
+        None => return None,
+    };
+    Some((name.to_string(), value))
+}
+
+/// Finds the `//PROFILE("<name>");` markers, or `PROFILE("<name>");` statements, and the `COUNT` markers.
+/// Returns them with the code tokens without them.
+fn find_profile_markers<'a>(source: &str, tokens: &[GlslToken<'a>]) -> (Vec<ProfileScope>, Vec<ProfileCounter>, Vec<GlslToken<'a>>) {
+    let write_count_help = "Write: //COUNT(\"<name>\"); or COUNT(\"<name>\", <value>);";
+    let mut markers = vec![];
+    let mut counters = vec![];
+    let mut code = vec![];
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = tokens[i];
+        if token.kind == TokenKind::Comment {
+            if let Some(rest) = token.text.strip_prefix("//PROFILE(\"") {
+                let name_end = rest.find("\");");
+                if name_end.is_none() {
+                    abort_call_site!("//PROFILE(\" doesn't end with \");"; note=token.text);
+                }
+                markers.push(ProfileScope::new(rest[..name_end.unwrap()].to_string(), token.offset..(token.offset + token.text.len()), code.len()));
+            }
+            if let Some(rest) = token.text.strip_prefix("//COUNT(") {
+                let arguments = rest.find(");").and_then(|end| counter_arguments(&rest[..end]));
+                if arguments.is_none() {
+                    abort_call_site!("Invalid counter: {}", token.text; help=write_count_help);
+                }
+                let (name, value) = arguments.unwrap();
+                counters.push(ProfileCounter { name, marker: token.offset..(token.offset + token.text.len()), value });
+            }
+            i += 1;
+            continue
+        }
+
+        if token.is("COUNT") && tokens.get(i + 1).is_some_and(|t| t.is("(")) {
+            let mut depth = 0;
+            let close = (i + 1..tokens.len()).find(|j| {
+                if tokens[*j].is("(") {
+                    depth += 1;
+                } else if tokens[*j].is(")") {
+                    depth -= 1;
+                }
+                depth == 0
+            });
+            let arguments = close
+                .filter(|close| tokens.get(close + 1).is_some_and(|t| t.is(";")))
+                .and_then(|close| counter_arguments(&source[(tokens[i + 1].offset + 1)..tokens[close].offset]));
+            if arguments.is_none() {
+                abort_call_site!("Invalid counter: {}", source[token.offset..].lines().next().unwrap_or_default(); help=write_count_help);
+            }
+            let (name, value) = arguments.unwrap();
+            let end = close.unwrap() + 1;
+            counters.push(ProfileCounter { name, marker: token.offset..(tokens[end].offset + 1), value });
+            i = end + 1;
+            continue
+        }
+
+        let is_statement = token.is("PROFILE")
+            && tokens.get(i + 1).is_some_and(|t| t.is("("))
+            && tokens.get(i + 2).is_some_and(|t| t.kind == TokenKind::String)
+            && tokens.get(i + 3).is_some_and(|t| t.is(")"))
+            && tokens.get(i + 4).is_some_and(|t| t.is(";"));
+        if is_statement {
+            let name = tokens[i + 2].text.trim_matches('"').to_string();
+            markers.push(ProfileScope::new(name, token.offset..(tokens[i + 4].offset + 1), code.len()));
+            i += 5;
+            continue
+        }
+
+        code.push(token);
+        i += 1;
+    }
+
+    if let Some(marker) = markers.iter().find(|marker| source[marker.marker.clone()].contains('\n')) {
+        abort_call_site!("//PROFILE(\"<name>\"); has to be on one line"; note=source[marker.marker.clone()]);
+    }
+    (markers, counters, code)
+}
+
+/// Index of the bracket closing the one at `open`, or the last token if it isn't closed.
+fn matching_bracket(code: &[GlslToken], open: usize) -> usize {
+    let mut depth = 0;
+    for (i, token) in code.iter().enumerate().skip(open) {
+        if token.is("(") || token.is("{") || token.is("[") {
+            depth += 1;
+        } else if token.is(")") || token.is("}") || token.is("]") {
+            depth -= 1;
+            if depth == 0 {
+                return i
+            }
+        }
+    }
+    code.len().saturating_sub(1)
+}
+
+/// Whether the shader declares a `layout(push_constant)` block. A stage can only have one.
+fn has_push_constant_block(code: &[GlslToken]) -> bool {
+    code.iter().enumerate()
+        .filter(|(i, token)| token.is("layout") && code.get(i + 1).is_some_and(|t| t.is("(")))
+        .any(|(i, _)| code[(i + 2)..matching_bracket(code, i + 1)].iter().any(|token| token.is("push_constant")))
+}
+
+/// Index of the last token of the statement starting at `start`.
+fn statement_end(code: &[GlslToken], start: usize) -> usize {
+    let token = code.get(start);
+    if token.is_none() {
+        return code.len().saturating_sub(1)
+    }
+    let token = token.unwrap();
+
+    if token.is("{") {
+        return matching_bracket(code, start)
+    }
+    if token.is("if") {
+        let end = statement_end(code, matching_bracket(code, start + 1) + 1);
+        if code.get(end + 1).is_some_and(|t| t.is("else")) {
+            return statement_end(code, end + 2)
+        }
+        return end
+    }
+    if token.is("for") || token.is("while") || token.is("switch") {
+        return statement_end(code, matching_bracket(code, start + 1) + 1)
+    }
+    if token.is("do") {
+        // do <statement> while (<condition>);
+        let body_end = statement_end(code, start + 1);
+        return (matching_bracket(code, body_end + 2) + 1).min(code.len().saturating_sub(1))
+    }
+
+    let mut depth = 0;
+    for (i, token) in code.iter().enumerate().skip(start) {
+        if token.is("(") || token.is("{") || token.is("[") {
+            depth += 1;
+        } else if token.is(")") || token.is("}") || token.is("]") {
+            if depth == 0 {
+                return i - 1
+            }
+            depth -= 1;
+        } else if token.is(";") && depth == 0 {
+            return i
+        }
+    }
+    code.len().saturating_sub(1)
+}
+
+/// The loop or `switch` a `break`, or the loop a `continue`, at `exit` jumps out of, as the index of its keyword.
+fn jump_target(code: &[GlslToken], exit: usize) -> Option<usize> {
+    let keywords: &[&str] = if code[exit].is("break") { &["for", "while", "do", "switch"] } else { &["for", "while", "do"] };
+    (0..exit).rev()
+        .filter(|i| keywords.iter().any(|keyword| code[*i].is(keyword)))
+        .find(|i| statement_end(code, *i) >= exit)
+}
+
+/// The return type of the function containing the token at `index`, e.g. `vec4` or `float[2]`.
+fn return_type<'a>(source: &'a str, code: &[GlslToken], index: usize) -> Option<&'a str> {
+    let mut depth = 0;
+    let mut body_start = None;
+    for (i, token) in code.iter().enumerate().take(index) {
+        if token.is("{") {
+            if depth == 0 {
+                body_start = Some(i);
+            }
+            depth += 1;
+        } else if token.is("}") {
+            depth -= 1;
+        }
+    }
+
+    // <type> <name>(<parameters>) {
+    let parameters_end = body_start?.checked_sub(1).filter(|i| code[*i].is(")"))?;
+    let mut depth = 0;
+    let parameters_start = (0..=parameters_end).rev().find(|i| {
+        if code[*i].is(")") {
+            depth += 1;
+        } else if code[*i].is("(") {
+            depth -= 1;
+        }
+        depth == 0
+    })?;
+    let type_end = parameters_start.checked_sub(2)?;
+    let type_start = if code[type_end].is("]") {
+        (0..type_end).rev().find(|i| code[*i].is("["))?.checked_sub(1)?
+    } else {
+        type_end
+    };
+
+    let end = code[type_end].offset + code[type_end].text.len();
+    Some(&source[code[type_start].offset..end])
+}
+
+/// Declares the ring buffer of `printf` records. A record is the format id followed by the arguments, `record` `uint`s in total.
+/// The host has to clear `head` to 0 before each dispatch. Nothing is written when the buffer can't hold a single record.
+fn printf_inject_code(options: &ProfileOptions, record: usize) -> String {
+    let printf_layout = format!("layout(set = {}, binding = {})", options.set, options.printf_binding);
+    format!(r#"
+{printf_layout} buffer ProfilerPrintf {{
+    uint head;
+    uint data[];
+}} profiler_printf;
+
+bool PROFILE_PRINTF_BEGIN(uint format, out uint base) {{
+    uint slots = uint(profiler_printf.data.length()) / {record};
+    if (!PROFILE_ACTIVE() || slots == 0) {{
+        return false;
+    }}
+    base = (atomicAdd(profiler_printf.head, 1u) % slots) * {record};
+    profiler_printf.data[base] = format;
+    return true;
 }}
     "#)
 }
 
-pub fn inject_profiler(mut source: String) -> (String, Vec<String>) {
+/// The type of the values a printf conversion like `%d` or `%v3f` prints: `i`, `u` or `f`, and the number of components.
+fn printf_conversions(format: &str) -> Result<Vec<(char, usize)>, String> {
+    let mut conversions = vec![];
+    let mut chars = format.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            continue
+        }
+        if chars.next_if_eq(&'%').is_some() {
+            continue
+        }
+
+        let mut spec = String::from("%");
+        while let Some(c) = chars.next_if(|c| "-+ #0123456789.".contains(*c)) {
+            spec.push(c);
+        }
+        let mut components = 1;
+        if chars.next_if_eq(&'v').is_some() {
+            spec.push('v');
+            let size = chars.next_if(|c| ('2'..='4').contains(c));
+            if size.is_none() {
+                return Err(spec)
+            }
+            components = size.unwrap().to_digit(10).unwrap() as usize;
+            spec.push(size.unwrap());
+        }
+
+        let conversion = chars.next();
+        let kind = match conversion {
+            Some('d' | 'i' | 'c') => 'i',
+            Some('u' | 'x' | 'X' | 'o') => 'u',
+            Some('f' | 'F' | 'e' | 'E' | 'g' | 'G') => 'f',
+            _ => {
+                spec.extend(conversion);
+                return Err(spec)
+            }
+        };
+        conversions.push((kind, components));
+    }
+    Ok(conversions)
+}
+
+/// Rewrites the `printf("<format>", <values>...);` call from `code[call]` to `code[end]` into a record of the printf buffer.
+/// Returns the replacement and the number of `uint`s of the record.
+fn printf_record_code(source: &str, code: &[GlslToken], call: usize, end: usize, printf_formats: &mut Vec<String>) -> (String, usize) {
+    let statement = &source[code[call].offset..(code[end].offset + 1)];
+
+    let mut arguments = vec![];
+    let mut depth = 0;
+    let mut argument_start = call + 2;
+    for i in (call + 2)..end {
+        if code[i].is("(") || code[i].is("[") || code[i].is("{") {
+            depth += 1;
+        } else if code[i].is(")") || code[i].is("]") || code[i].is("}") {
+            depth -= 1;
+        }
+        if (depth == 0 && code[i].is(",")) || depth < 0 {
+            if argument_start < i {
+                arguments.push(&source[code[argument_start].offset..(code[i - 1].offset + code[i - 1].text.len())]);
+            }
+            argument_start = i + 1;
+        }
+    }
+
+    let format = arguments.first().and_then(|format| format.strip_prefix('"')?.strip_suffix('"'));
+    if format.is_none() {
+        abort_call_site!("printf needs a string literal as format"; note=statement);
+    }
+    let format = format.unwrap();
+    let conversions = printf_conversions(format);
+    if let Err(spec) = conversions {
+        abort_call_site!("Unsupported printf conversion: {}", spec; note=statement; help="Use %d, %i, %u, %x, %X, %o, %c, %f, %F, %e, %E, %g, %G, with %v<2-4><conversion> for vectors");
+    }
+    let conversions = conversions.unwrap();
+    if conversions.len() != arguments.len() - 1 {
+        abort_call_site!("The printf format expects {} values, but {} are given", conversions.len(), arguments.len() - 1; note=statement);
+    }
+
+    let id = printf_formats.iter().position(|known| known == format).unwrap_or_else(|| {
+        printf_formats.push(format.to_string());
+        printf_formats.len() - 1
+    });
+
+    // The values are evaluated on every invocation, so arguments with side effects like atomicAdd still run everywhere.
+    let mut values = String::new();
+    let mut writes = String::new();
+    let mut word = 1;
+    for (n, ((kind, components), value)) in conversions.into_iter().zip(&arguments[1..]).enumerate() {
+        let (scalar, vector) = match kind {
+            'i' => ("int", "ivec"),
+            'u' => ("uint", "uvec"),
+            _ => ("float", "vec"),
+        };
+        let (value_type, bits_type) = if components == 1 {
+            (scalar.to_string(), "uint".to_string())
+        } else {
+            (format!("{vector}{components}"), format!("uvec{components}"))
+        };
+        let bits = if kind == 'f' {
+            format!("floatBitsToUint({value_type}({value}))")
+        } else {
+            format!("{bits_type}({value_type}({value}))")
+        };
+
+        values.push_str(&format!(" {bits_type} profile_printf_value{n} = {bits};"));
+        for component in ["", ".x", ".y", ".z", ".w"].iter().skip(if components == 1 { 0 } else { 1 }).take(components) {
+            writes.push_str(&format!(" profiler_printf.data[profile_printf_at + {word}] = profile_printf_value{n}{component};"));
+            word += 1;
+        }
+    }
+    let record = format!("{{{values} uint profile_printf_at; if (PROFILE_PRINTF_BEGIN({id}u, profile_printf_at)) {{{writes} }} }}");
+    (record, word)
+}
+
+/// Instruments the `//PROFILE("<name>")` scopes and `COUNT` counters of `source`.
+/// Returns the shader and the scope and counter names with the metadata for the host.
+/// A scope ends at the end of its block and at every `return`, `discard` or `break`/`continue` leaving it.
+/// Scopes outside of any other scope, e.g. in functions, have no parent.
+pub fn inject_profiler(source: String, options: &ProfileOptions, glsl_type: shaderc::ShaderKind) -> (String, ProfileMetadata) {
+    let source = match &options.functions {
+        Some(patterns) => insert_function_markers(&source, patterns),
+        None => source,
+    };
+    let tokens = tokenize(&source);
+    let (mut scopes, counters, code) = find_profile_markers(&source, &tokens);
+    if options.push_constant && !options.aggregate && has_push_constant_block(&code) {
+        abort_call_site!("The shader already has a push_constant block, ProfilerIn can't be a second one";
+            help="Bind ProfilerIn as uniform buffer with profile = {{ in_binding = <n> }}");
+    }
+
     let mut profile_scope_names = vec![];
+    for scope in scopes.iter_mut() {
+        let mut depth = 0;
+        let end = code.iter().enumerate().skip(scope.start).find(|(_, token)| {
+            if token.is("{") {
+                depth += 1;
+            } else if token.is("}") {
+                depth -= 1;
+            }
+            depth < 0
+        });
+        if end.is_none() {
+            abort_call_site!("//PROFILE(\"<name>\"); in invalid scope. No closing bracket found!"; note=source[scope.marker.start..]);
+        }
+
+        get_id(&scope.name, &mut profile_scope_names);
+        scope.end = end.unwrap().0;
+    }
 
-    let mut main_scope_placed = false;
-    let mut start_offset = 0;
+    let only_printf = options.printf && scopes.is_empty() && counters.is_empty();
+    if !scopes.iter().any(|scope| scope.name == "main") && !only_printf {
+        abort_call_site!("Please place a //PROFILE(\"main\") at the start of the main function.");
+    }
+
+    let mut edits: Vec<(Range<usize>, String)> = vec![];
+    for scope in &scopes {
+        let with_init = scope.name == "main";
+        edits.push((scope.marker.clone(), profile_begin_code(&scope.name, &mut profile_scope_names, with_init)));
+    }
 
-    loop {
-        let profile_start = source[start_offset..].find("//PROFILE(\"");
-        if profile_start.is_none() {
-            break
+    let mut counter_ids = vec![];
+    for counter in &counters {
+        if scopes.iter().any(|scope| scope.name == counter.name) {
+            abort_call_site!("{} is used as profile scope and as counter", counter.name);
         }
-        let profile_start = profile_start.unwrap() + start_offset;
-        start_offset = profile_start;
 
-        let include_line_end = source[profile_start..].find("\");");
-        if include_line_end.is_none() {
-            abort_call_site!("//PROFILE(\" doesn't end with \");"; note=source[profile_start..]);
+        let id = get_id(&counter.name, &mut profile_scope_names);
+        if !counter_ids.contains(&id) {
+            counter_ids.push(id);
         }
-        let include_line_end = include_line_end.unwrap() + profile_start + 3;
-        
-        let code_end_index = find_closing_bracket(&source[profile_start..]);
-        if code_end_index.is_none() {
-            abort_call_site!("//PROFILE(\"<name>\"); in invalid scope. No closing bracket found!"; note=source[profile_start..]);
+        edits.push((counter.marker.clone(), format!("PROFILE_COUNT({id}, uint({}));", counter.value)));
+    }
+
+    // Scopes ending at the same `}` end the innermost first.
+    for (i, scope) in scopes.iter().enumerate() {
+        if scopes[..i].iter().any(|other| other.end == scope.end) {
+            continue
         }
-        let code_end_index = code_end_index.unwrap() + profile_start + 1;
+        let end_code: String = scopes.iter().rev()
+            .filter(|other| other.end == scope.end)
+            .map(|other| profile_end_code(&other.name, &mut profile_scope_names))
+            .collect();
+        let offset = code[scope.end].offset;
+        edits.push((offset..offset, end_code));
+    }
 
-        let name = &source[(profile_start + 11)..(include_line_end - 3)].to_string();
-        let main_scope = name == "main";
-        main_scope_placed |= main_scope;
+    let exits = ["return", "discard", "break", "continue", "terminateInvocation", "ignoreIntersectionEXT", "terminateRayEXT"];
+    for (i, token) in code.iter().enumerate() {
+        if !exits.iter().any(|exit| token.is(exit)) {
+            continue
+        }
 
-        let return_starts: Vec<usize> = source[profile_start..code_end_index].match_indices("return")
-            .map(|(i, _)|i + profile_start)
+        let is_jump = token.is("break") || token.is("continue");
+        let target = if is_jump { jump_target(&code, i) } else { None };
+        if is_jump && target.is_none() {
+            continue
+        }
+        let left_scopes: Vec<&ProfileScope> = scopes.iter().rev()
+            .filter(|scope| scope.start <= i && i < scope.end)
+            .filter(|scope| target.is_none_or(|target| target < scope.start))
             .collect();
+        if left_scopes.is_empty() {
+            continue
+        }
 
+        let end_code: String = left_scopes.iter()
+            .map(|scope| profile_end_code(&scope.name, &mut profile_scope_names))
+            .collect();
+        let statement_end = statement_end(&code, i);
+        let statement = token.offset..(code[statement_end].offset + code[statement_end].text.len());
 
-        // Replace parts
-        source.replace_range(code_end_index..code_end_index, &profile_end_code(&name, &mut profile_scope_names));
+        // The returned value has to be evaluated before the scope ends.
+        let has_value = token.is("return") && statement_end > i + 1;
+        let replacement = if has_value {
+            let return_type = return_type(&source, &code, i);
+            if return_type.is_none() {
+                abort_call_site!("Can't find the return type of the function around //PROFILE(\"<name>\");"; note=source[statement.clone()]);
+            }
+            let value = &source[code[i + 1].offset..code[statement_end].offset];
+            format!("{{ {} profile_return_value = {value}; {end_code}return profile_return_value; }}", return_type.unwrap())
+        } else {
+            format!("{{ {end_code}{} }}", &source[statement.clone()])
+        };
+        edits.push((statement, replacement));
+    }
 
-        for return_start in return_starts.into_iter().rev() {
-            source.replace_range(return_start..return_start, &profile_end_code(&name, &mut profile_scope_names));
+    let mut printf_formats = vec![];
+    let mut printf_record = 1;
+    for (i, token) in code.iter().enumerate() {
+        let is_call = (token.is("printf") || token.is("debugPrintfEXT")) && code.get(i + 1).is_some_and(|t| t.is("("));
+        if !options.printf || !is_call {
+            continue
         }
 
-        source.replace_range(profile_start..include_line_end, &profile_begin_code(&name, &mut profile_scope_names, main_scope));
+        let end = matching_bracket(&code, i + 1) + 1;
+        if !code.get(end).is_some_and(|t| t.is(";")) {
+            abort_call_site!("printf(...) has to be a statement"; note=source[token.offset..].lines().next().unwrap_or_default());
+        }
+        let (record, words) = printf_record_code(&source, &code, i, end, &mut printf_formats);
+        printf_record = printf_record.max(words);
+        edits.push((token.offset..(code[end].offset + 1), record));
     }
 
-    if !main_scope_placed {
-        abort_call_site!("Please place a //PROFILE(\"main\") at the start of the main function.");
+    let mut parents = vec![];
+    for name in profile_scope_names.iter() {
+        if name == "main" || counters.iter().any(|counter| counter.name == *name) {
+            parents.push(None);
+            continue
+        }
+        let scope = scopes.iter().position(|scope| scope.name == *name).unwrap();
+        let parent = scopes[..scope].iter()
+            .rfind(|other| other.start <= scopes[scope].start && scopes[scope].start <= other.end)
+            .map(|other| other.name.as_str())
+            .filter(|parent| parent != name);
+        parents.push(parent.and_then(|parent| profile_scope_names.iter().position(|scope| scope == parent)));
+    }
+
+    let mut source = source;
+    edits.sort_by_key(|(range, _)| std::cmp::Reverse(range.start));
+    for (range, replacement) in edits {
+        source.replace_range(range, &replacement);
     }
  
     let version_start = source.find("#version");
@@ -140,7 +984,202 @@ pub fn inject_profiler(mut source: String) -> (String, Vec<String>) {
     }
     let version_line_end = version_line_end.unwrap() + version_start + 1;
 
-    source.replace_range(version_line_end..version_line_end, &profile_inject_code(profile_scope_names.len()));
+    let mut inject_code = profile_inject_code(profile_scope_names.len(), options, glsl_type);
+    if options.printf {
+        inject_code.push_str(&printf_inject_code(options, printf_record));
+    }
+    source.replace_range(version_line_end..version_line_end, &inject_code);
+
+    let metadata = ProfileMetadata {
+        names: profile_scope_names,
+        parents,
+        counters: counter_ids,
+        printf_formats,
+        printf_record,
+    };
+    (source, metadata)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn code(source: &str) -> Vec<GlslToken<'_>> {
+        tokenize(source).into_iter().filter(|token| token.kind != TokenKind::Comment).collect()
+    }
+
+    #[test]
+    fn push_constant_block() {
+        assert!(has_push_constant_block(&code("layout(push_constant) uniform Constants { uint frame; } constants;")));
+        assert!(has_push_constant_block(&code("layout(std430, push_constant) uniform Constants { uint frame; };")));
+        assert!(!has_push_constant_block(&code("// layout(push_constant) uniform Constants { uint frame; };")));
+        assert!(!has_push_constant_block(&code("layout(binding = 0) uniform Params { uint push_constant; };")));
+    }
+
+    #[test]
+    fn stage_selectors() {
+        let source = "#version 460 core\nvoid main () {\n    //PROFILE(\"main\");\n}\n";
+        let stages = [
+            (shaderc::ShaderKind::Mesh, "GL_EXT_mesh_shader", "gl_WorkGroupID.x, gl_LocalInvocationIndex"),
+            (shaderc::ShaderKind::Task, "GL_EXT_mesh_shader", "gl_WorkGroupID.x, gl_LocalInvocationIndex"),
+            (shaderc::ShaderKind::RayGeneration, "GL_EXT_ray_tracing", "gl_LaunchIDEXT.xy"),
+            (shaderc::ShaderKind::Intersection, "GL_EXT_ray_tracing", "gl_LaunchIDEXT.xy"),
+            (shaderc::ShaderKind::Callable, "GL_EXT_ray_tracing", "gl_LaunchIDEXT.xy"),
+        ];
+        for (stage, extension, selector) in stages {
+            let (shader, _) = inject_profiler(source.to_string(), &ProfileOptions::default(), stage);
+            assert!(shader.contains(&format!("#extension {extension} : require")), "{stage:?}");
+            assert!(shader.contains(selector), "{stage:?}");
+        }
+    }
 
-    (source, profile_scope_names)
+    #[test]
+    fn overloads_get_a_scope_each() {
+        let source = r#"#version 450 core
+vec3 to_linear(vec3 srgb) {
+    return pow(srgb, vec3(2.2));
+}
+vec4 to_linear(const in vec4 srgba) {
+    return vec4(to_linear(srgba.rgb), srgba.a);
+}
+float scale(float x) {
+    return x * 2.0;
+}
+void main () {
+    vec4 color = to_linear(vec4(scale(0.5)));
+}
+"#;
+        let options = ProfileOptions { functions: Some(vec![]), ..ProfileOptions::default() };
+        let (shader, metadata) = inject_profiler(source.to_string(), &options, shaderc::ShaderKind::Compute);
+        assert_eq!(metadata.names, ["to_linear(vec3)", "to_linear(vec4)", "scale", "main"]);
+        assert!(shader.contains("vec3 to_linear(vec3 srgb) { PROFILE_SCOPE_BEING(0);"), "{shader}");
+        assert!(shader.contains("vec4 to_linear(const in vec4 srgba) { PROFILE_SCOPE_BEING(1);"), "{shader}");
+    }
+
+    /// The instrumented shader without the injected header.
+    fn instrument(source: &str, options: &ProfileOptions) -> String {
+        let (shader, _) = inject_profiler(source.to_string(), options, shaderc::ShaderKind::Compute);
+        let body_start = shader.find("layout(binding = 0").unwrap();
+        shader[body_start..].to_string()
+    }
+
+    const EXITS_SHADER: &str = r#"#version 450 core
+layout(binding = 0, rgba8) uniform writeonly image2D img;
+
+vec4 shade(uvec2 pos) {
+    //PROFILE("shade");
+    vec4 returnColor = vec4(pos, 0.0, 1.0); // return in a comment
+    if (pos.x == 0) return vec4(0.0);
+    return returnColor;
+}
+
+void main () {
+    //PROFILE("main");
+    uvec2 pos = gl_GlobalInvocationID.xy;
+    vec4 color = vec4(0.0);
+    for (uint i = 0; i < 4; i++) {
+        //PROFILE("loop");
+        if (i == 2) continue;
+        for (uint j = 0; j < 2; j++) {
+            if (j == 1) break;
+        }
+        switch (i) {
+            case 3: break;
+            default: color += shade(pos + i);
+        }
+        if (color.x > 4.0) break; else if (color.y > 3.0) return;
+    }
+    imageStore(img, ivec2(pos), color);
+}
+"#;
+
+    #[test]
+    fn returns_evaluate_the_value_before_the_scope_ends() {
+        let shader = instrument(EXITS_SHADER, &ProfileOptions::default());
+
+        assert!(shader.contains("    PROFILE_SCOPE_BEING(0);\n    vec4 returnColor = vec4(pos, 0.0, 1.0); // return in a comment\n"));
+        assert!(shader.contains("if (pos.x == 0) { vec4 profile_return_value = vec4(0.0); PROFILE_SCOPE_END(0); return profile_return_value; }"));
+        assert!(shader.contains("    { vec4 profile_return_value = returnColor; PROFILE_SCOPE_END(0); return profile_return_value; }\nPROFILE_SCOPE_END(0); }"));
+    }
+
+    #[test]
+    fn jumps_end_only_the_scopes_they_leave() {
+        let shader = instrument(EXITS_SHADER, &ProfileOptions::default());
+
+        assert!(shader.contains("    PROFILE_INIT(); PROFILE_SCOPE_BEING(1);\n"));
+        assert!(shader.contains("if (i == 2) { PROFILE_SCOPE_END(2); continue; }"));
+        // The inner loop and the switch catch these breaks, they don't leave `loop`.
+        assert!(shader.contains("            if (j == 1) break;\n"));
+        assert!(shader.contains("            case 3: break;\n"));
+        assert!(shader.contains("if (color.x > 4.0) { PROFILE_SCOPE_END(2); break; } else if (color.y > 3.0) { PROFILE_SCOPE_END(2); PROFILE_SCOPE_END(1); return; }"));
+        assert!(shader.contains("    PROFILE_SCOPE_END(2); }\n    imageStore(img, ivec2(pos), color);\nPROFILE_SCOPE_END(1); }"));
+    }
+
+    const PRINTF_SHADER: &str = r#"#version 450 core
+layout(binding = 0, rgba8) uniform writeonly image2D img;
+
+void main () {
+    uvec2 pos = gl_GlobalInvocationID.xy;
+    printf("pos %v2u", pos);
+    printf("value %5.2f", float(pos.x) * 0.5);
+    if (pos.x == 0) printf("zero %d at %d", int(pos.y), -1);
+    imageStore(img, ivec2(pos), vec4(1.0));
+}
+"#;
+
+    #[test]
+    fn printf_records() {
+        let options = ProfileOptions { printf: true, ..ProfileOptions::default() };
+        let (shader, metadata) = inject_profiler(PRINTF_SHADER.to_string(), &options, shaderc::ShaderKind::Compute);
+        assert_eq!(metadata.printf_formats, vec!["pos %v2u", "value %5.2f", "zero %d at %d"]);
+        assert_eq!(metadata.printf_record, 3);
+        assert!(shader.contains("uint slots = uint(profiler_printf.data.length()) / 3;\n    if (!PROFILE_ACTIVE() || slots == 0) {"));
+
+        let shader = instrument(PRINTF_SHADER, &options);
+        assert!(shader.contains(concat!(
+            "    { uvec2 profile_printf_value0 = uvec2(uvec2(pos));",
+            " uint profile_printf_at; if (PROFILE_PRINTF_BEGIN(0u, profile_printf_at)) {",
+            " profiler_printf.data[profile_printf_at + 1] = profile_printf_value0.x;",
+            " profiler_printf.data[profile_printf_at + 2] = profile_printf_value0.y; } }\n",
+        )));
+        assert!(shader.contains(concat!(
+            "    { uint profile_printf_value0 = floatBitsToUint(float(float(pos.x) * 0.5));",
+            " uint profile_printf_at; if (PROFILE_PRINTF_BEGIN(1u, profile_printf_at)) {",
+            " profiler_printf.data[profile_printf_at + 1] = profile_printf_value0; } }\n",
+        )));
+    }
+
+    #[test]
+    fn printf_arguments_run_on_every_invocation() {
+        let source = r#"#version 450 core
+layout(binding = 0) buffer Counter { uint count; } counter;
+
+void main () {
+    uint i = 0;
+    printf("ticket %u step %u", atomicAdd(counter.count, 1u), i++);
+}
+"#;
+        let options = ProfileOptions { printf: true, ..ProfileOptions::default() };
+        let (shader, _) = inject_profiler(source.to_string(), &options, shaderc::ShaderKind::Compute);
+        assert!(shader.contains(concat!(
+            "    { uint profile_printf_value0 = uint(uint(atomicAdd(counter.count, 1u)));",
+            " uint profile_printf_value1 = uint(uint(i++));",
+            " uint profile_printf_at; if (PROFILE_PRINTF_BEGIN(0u, profile_printf_at)) {",
+        )), "{shader}");
+    }
+
+    #[test]
+    fn printf_in_unbraced_if() {
+        let options = ProfileOptions { printf: true, ..ProfileOptions::default() };
+        let shader = instrument(PRINTF_SHADER, &options);
+
+        // The record is one block, so it stays the body of the if.
+        assert!(shader.contains(concat!(
+            "    if (pos.x == 0) { uint profile_printf_value0 = uint(int(int(pos.y)));",
+            " uint profile_printf_value1 = uint(int(-1));",
+            " uint profile_printf_at; if (PROFILE_PRINTF_BEGIN(2u, profile_printf_at)) {",
+            " profiler_printf.data[profile_printf_at + 1] = profile_printf_value0;",
+            " profiler_printf.data[profile_printf_at + 2] = profile_printf_value1; } }\n",
+        )));
+    }
 }
@@ -0,0 +1,207 @@
+use crate::span_map::block_comment_len;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum RustTokenKind {
+    Ident,
+    Literal,
+    Punct,
+}
+
+/// A token of Rust source with its byte range. Comments and whitespace are dropped.
+#[derive(Clone, Copy, Debug)]
+struct RustToken {
+    kind: RustTokenKind,
+    start: usize,
+    end: usize,
+}
+
+/// A `glsl!{type = Include, name = "<name>", code = {<glsl>}}` marco found in a Rust file.
+pub struct IncludeMarco {
+    pub name: String,
+    /// The exact text between the braces of `code = {}`.
+    pub code: String,
+    /// 1-based line of the `glsl!` invocation.
+    pub line: usize,
+}
+
+/// Finds all `type = Include` marcos of a Rust file. Comments and string literals are skipped, so only real invocations count.
+pub fn find_include_marcos(content: &str) -> Vec<IncludeMarco> {
+    let tokens = tokenize_rust(content);
+    let text = |token: &RustToken| &content[token.start..token.end];
+
+    let mut marcos = vec![];
+    let mut i = 0;
+    while i + 2 < tokens.len() {
+        let is_invocation = tokens[i].kind == RustTokenKind::Ident && text(&tokens[i]) == "glsl"
+            && text(&tokens[i + 1]) == "!"
+            && ["{", "(", "["].contains(&text(&tokens[i + 2]));
+        if !is_invocation {
+            i += 1;
+            continue;
+        }
+
+        let close = matching_close(content, &tokens, i + 2);
+        if close.is_none() {
+            break
+        }
+        let close = close.unwrap();
+
+        let arguments = marco_arguments(content, &tokens[(i + 3)..close]);
+        let value = |key: &str| arguments.iter().find(|(k, _)| *k == key).map(|(_, v)| *v);
+        let is_include = value("type").is_some_and(|v| v.len() == 1 && text(&v[0]) == "Include");
+        let name = value("name").filter(|v| v.len() == 1 && v[0].kind == RustTokenKind::Literal);
+        let code = value("code").filter(|v| v.len() >= 2 && text(&v[0]) == "{");
+
+        if let (true, Some(name), Some(code)) = (is_include, name, code) {
+            let code_start = code[0].end;
+            let code_end = code[code.len() - 1].start;
+            marcos.push(IncludeMarco {
+                name: text(&name[0]).trim_matches('"').to_string(),
+                code: content[code_start..code_end].to_string(),
+                line: content[..tokens[i].start].matches('\n').count() + 1,
+            });
+        }
+        i = close + 1;
+    }
+
+    marcos
+}
+
+/// Splits the tokens inside a marco into `key = <value tokens>` pairs separated by top level `,`.
+fn marco_arguments<'c, 't>(content: &'c str, tokens: &'t [RustToken]) -> Vec<(&'c str, &'t [RustToken])> {
+    let mut arguments = vec![];
+    let mut depth = 0;
+    let mut argument_start = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        let text = &content[token.start..token.end];
+        match text {
+            "{" | "(" | "[" => depth += 1,
+            "}" | ")" | "]" => depth -= 1,
+            _ => {}
+        }
+
+        let is_separator = depth == 0 && (text == "," || text == ";") && token.kind == RustTokenKind::Punct;
+        if is_separator || i == tokens.len() - 1 {
+            let end = if is_separator { i } else { i + 1 };
+            let argument = &tokens[argument_start..end];
+            if argument.len() >= 2 && content[argument[1].start..argument[1].end] == *"=" {
+                arguments.push((&content[argument[0].start..argument[0].end], &argument[2..]));
+            }
+            argument_start = i + 1;
+        }
+    }
+    arguments
+}
+
+/// Index of the token closing the group opened at `open`.
+fn matching_close(content: &str, tokens: &[RustToken], open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, token) in tokens.iter().enumerate().skip(open) {
+        if token.kind != RustTokenKind::Punct {
+            continue;
+        }
+        match &content[token.start..token.end] {
+            "{" | "(" | "[" => depth += 1,
+            "}" | ")" | "]" => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn tokenize_rust(content: &str) -> Vec<RustToken> {
+    let bytes = content.as_bytes();
+    let mut tokens = vec![];
+    let mut offset = 0;
+
+    while offset < content.len() {
+        let rest = &content[offset..];
+        let c = rest.chars().next().unwrap();
+
+        if c.is_whitespace() {
+            offset += c.len_utf8();
+            continue;
+        }
+        if rest.starts_with("//") {
+            offset += rest.find('\n').unwrap_or(rest.len());
+            continue;
+        }
+        if rest.starts_with("/*") {
+            offset += block_comment_len(rest);
+            continue;
+        }
+
+        let (kind, len) = if let Some(len) = string_literal_len(rest) {
+            (RustTokenKind::Literal, len)
+        } else if c == '\'' {
+            let mut chars = rest[1..].chars();
+            let first = chars.next();
+            let is_char_literal = first == Some('\\') || chars.next() == Some('\'');
+            if is_char_literal {
+                let mut len = 1;
+                while len < rest.len() && !rest[len..].starts_with('\'') {
+                    len += if rest[len..].starts_with('\\') { 2 } else { rest[len..].chars().next().unwrap().len_utf8() };
+                }
+                (RustTokenKind::Literal, (len + 1).min(rest.len()))
+            } else {
+                // A lifetime or label.
+                let len = rest[1..].find(|c: char| !(c.is_alphanumeric() || c == '_')).map(|i| i + 1).unwrap_or(rest.len());
+                (RustTokenKind::Ident, len)
+            }
+        } else if c.is_alphabetic() || c == '_' {
+            let start = if rest.starts_with("r#") { 2 } else { 0 };
+            (RustTokenKind::Ident, rest[start..].find(|c: char| !(c.is_alphanumeric() || c == '_')).map(|i| i + start).unwrap_or(rest.len()))
+        } else if c.is_ascii_digit() {
+            let mut len = rest.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(rest.len());
+            // A decimal point, but not a range `..` or a method call.
+            if bytes.get(offset + len) == Some(&b'.') && bytes.get(offset + len + 1).is_some_and(|b| b.is_ascii_digit()) {
+                len += 1 + rest[(len + 1)..].find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(rest.len() - len - 1);
+            }
+            (RustTokenKind::Literal, len)
+        } else {
+            (RustTokenKind::Punct, c.len_utf8())
+        };
+
+        tokens.push(RustToken { kind, start: offset, end: offset + len });
+        offset += len;
+    }
+
+    tokens
+}
+
+/// Length of a string literal `"..."`, `b"..."`, `c"..."` or raw `r#"..."#` at the start of `text`.
+fn string_literal_len(text: &str) -> Option<usize> {
+    let prefix_len = ["br", "cr", "b", "c", "r", ""].iter()
+        .find(|prefix| text.starts_with(*prefix) && text[prefix.len()..].trim_start_matches('#').starts_with('"'))
+        .map(|prefix| prefix.len())?;
+    let is_raw = text[..prefix_len].ends_with('r');
+    let hashes = text[prefix_len..].len() - text[prefix_len..].trim_start_matches('#').len();
+    if hashes > 0 && !is_raw {
+        return None
+    }
+
+    let body_start = prefix_len + hashes + 1;
+    if is_raw {
+        let terminator = format!("\"{}", "#".repeat(hashes));
+        let end = text[body_start..].find(&terminator).map(|i| i + body_start + terminator.len());
+        return Some(end.unwrap_or(text.len()))
+    }
+
+    let mut len = body_start;
+    while len < text.len() {
+        let rest = &text[len..];
+        if let Some(escaped) = rest.strip_prefix('\\') {
+            len += 1 + escaped.chars().next().map(char::len_utf8).unwrap_or(0);
+        } else if rest.starts_with('"') {
+            return Some(len + 1)
+        } else {
+            len += rest.chars().next().unwrap().len_utf8();
+        }
+    }
+    Some(text.len())
+}
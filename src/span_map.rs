@@ -0,0 +1,254 @@
+use proc_macro2::{Delimiter, Span, TokenStream, TokenTree};
+
+struct IndexedToken {
+    start: usize,
+    end: usize,
+    span: Span,
+}
+
+/// Maps byte offsets in the GLSL text of a `code = {<glsl>}` group back to the spans of its Rust tokens.
+pub struct SpanIndex {
+    code: String,
+    tokens: Vec<IndexedToken>,
+    open_line: usize,
+    open_column: usize,
+}
+
+impl SpanIndex {
+    /// `code` is the source text of `code_group` without the surrounding braces.
+    pub fn new(code_group: &TokenTree, code: &str) -> SpanIndex {
+        let open = match code_group {
+            TokenTree::Group(group) => group.span_open(),
+            _ => code_group.span(),
+        };
+        let (open_line, open_column) = line_column(open);
+
+        let mut index = SpanIndex {
+            code: code.to_string(),
+            tokens: vec![],
+            open_line,
+            open_column,
+        };
+
+        let mut cursor = 0;
+        if let TokenTree::Group(group) = code_group {
+            index.add_stream(group.stream(), &mut cursor);
+        }
+        index
+    }
+
+    /// The span of the token containing the byte `offset` of the code.
+    pub fn span_at(&self, offset: usize) -> Option<Span> {
+        self.tokens.iter()
+            .find(|t| t.start <= offset && offset < t.end)
+            .map(|t| t.span)
+    }
+
+    /// The span of the token on the 0-based `line` of the code that is at the 1-based `column` or matches `key`.
+    /// Falls back to the first token of the line.
+    pub fn find(&self, line: usize, column: Option<usize>, key: &str) -> Option<Span> {
+        let line_start = self.line_start(line)?;
+        let line_end = self.code[line_start..].find('\n').map(|i| i + line_start).unwrap_or(self.code.len());
+        let on_line: Vec<&IndexedToken> = self.tokens.iter()
+            .filter(|t| t.start >= line_start && t.start < line_end)
+            .collect();
+
+        if let Some(column) = column {
+            let offset = char_to_byte_offset(&self.code[line_start..line_end], column.saturating_sub(1)) + line_start;
+            if let Some(span) = self.span_at(offset) {
+                return Some(span);
+            }
+        }
+
+        if !key.is_empty() {
+            let exact = on_line.iter().find(|t| &self.code[t.start..t.end] == key);
+            let prefix = on_line.iter().find(|t| self.code[t.start..].starts_with(key));
+            if let Some(token) = exact.or(prefix) {
+                return Some(token.span);
+            }
+        }
+
+        on_line.first().map(|t| t.span)
+    }
+
+    fn line_start(&self, line: usize) -> Option<usize> {
+        if line == 0 {
+            return Some(0);
+        }
+        self.code.match_indices('\n').nth(line - 1).map(|(i, _)| i + 1)
+    }
+
+    fn add_stream(&mut self, stream: TokenStream, cursor: &mut usize) {
+        for token in stream {
+            match token {
+                TokenTree::Group(group) => {
+                    let (open, close) = match group.delimiter() {
+                        Delimiter::Brace => ("{", "}"),
+                        Delimiter::Bracket => ("[", "]"),
+                        Delimiter::Parenthesis => ("(", ")"),
+                        Delimiter::None => ("", ""),
+                    };
+                    self.add_token(open, group.span_open(), cursor);
+                    self.add_stream(group.stream(), cursor);
+                    self.add_token(close, group.span_close(), cursor);
+                }
+                TokenTree::Ident(ident) => self.add_token(&ident.to_string(), ident.span(), cursor),
+                TokenTree::Punct(punct) => self.add_token(&punct.as_char().to_string(), punct.span(), cursor),
+                TokenTree::Literal(literal) => self.add_token(&literal.to_string(), literal.span(), cursor),
+            }
+        }
+    }
+
+    fn add_token(&mut self, text: &str, span: Span, cursor: &mut usize) {
+        if text.is_empty() {
+            return;
+        }
+
+        let start = self.located_offset(span)
+            .filter(|start| self.code[*start..].starts_with(text))
+            .or_else(|| self.scanned_offset(text, *cursor));
+
+        if let Some(start) = start {
+            *cursor = start + text.len();
+            self.tokens.push(IndexedToken { start, end: *cursor, span });
+        }
+    }
+
+    /// Offset from the span's line and column, relative to the opening brace of the code group.
+    fn located_offset(&self, span: Span) -> Option<usize> {
+        let (line, column) = line_column(span);
+        if line < self.open_line || line == self.open_line && column <= self.open_column {
+            return None;
+        }
+
+        if line == self.open_line {
+            let first_line = &self.code[..self.code.find('\n').unwrap_or(self.code.len())];
+            Some(char_to_byte_offset(first_line, column - self.open_column - 1))
+        } else {
+            let line_start = self.line_start(line - self.open_line)?;
+            Some(char_to_byte_offset(&self.code[line_start..], column - 1) + line_start)
+        }
+    }
+
+    /// Offset of `text` after skipping whitespace and Rust comments from `cursor`.
+    fn scanned_offset(&self, text: &str, mut cursor: usize) -> Option<usize> {
+        loop {
+            let rest = &self.code[cursor..];
+            let trimmed = rest.trim_start();
+            cursor += rest.len() - trimmed.len();
+
+            if trimmed.starts_with("//") {
+                cursor += trimmed.find('\n').unwrap_or(trimmed.len());
+            } else if trimmed.starts_with("/*") {
+                cursor += block_comment_len(trimmed);
+            } else {
+                break;
+            }
+        }
+
+        if self.code[cursor..].starts_with(text) {
+            Some(cursor)
+        } else {
+            self.code[cursor..].find(text).map(|i| i + cursor)
+        }
+    }
+}
+
+/// Length of the (possibly nested) Rust block comment at the start of `text`.
+pub fn block_comment_len(text: &str) -> usize {
+    let mut depth = 0;
+    let mut i = 0;
+    while i < text.len() {
+        if text[i..].starts_with("/*") {
+            depth += 1;
+            i += 2;
+        } else if text[i..].starts_with("*/") {
+            depth -= 1;
+            i += 2;
+            if depth == 0 {
+                return i;
+            }
+        } else {
+            i += text[i..].chars().next().unwrap().len_utf8();
+        }
+    }
+    text.len()
+}
+
+/// The 1-based line and column of the start of `span`.
+fn line_column(span: Span) -> (usize, usize) {
+    let start = span.start();
+    (start.line, start.column + 1)
+}
+
+fn char_to_byte_offset(text: &str, chars: usize) -> usize {
+    text.char_indices().nth(chars).map(|(i, _)| i).unwrap_or(text.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proc_macro2::LineColumn;
+    use std::str::FromStr;
+
+    const CODE_GROUP: &str = r#"{
+    #version 450 core
+    layout(binding = 0, rgba8) uniform writeonly image2D img;
+    void main () {
+        debugPrintfEXT("first line
+second line");
+        imageStore(img, ivec2(0), vec4(1.0));
+    }
+}"#;
+
+    fn index() -> SpanIndex {
+        let code_group = TokenStream::from_str(CODE_GROUP).unwrap().into_iter().next().unwrap();
+        SpanIndex::new(&code_group, &CODE_GROUP[1..(CODE_GROUP.len() - 1)])
+    }
+
+    /// 1-based line and 0-based column in `CODE_GROUP` of what `find` returned.
+    fn found(span: Option<Span>) -> Option<(usize, usize)> {
+        span.map(|span| span.start()).map(|LineColumn { line, column }| (line, column))
+    }
+
+    #[test]
+    fn find_by_column() {
+        let index = index();
+        assert_eq!(found(index.find(6, Some(9), "imageStore")), Some((7, 8)));
+        assert_eq!(found(index.find(6, Some(20), "imageStore")), Some((7, 19)));
+        assert_eq!(found(index.find(2, Some(40), "")), Some((3, 39)));
+    }
+
+    #[test]
+    fn find_by_key() {
+        let index = index();
+        assert_eq!(found(index.find(6, None, "ivec2")), Some((7, 24)));
+        assert_eq!(found(index.find(6, None, "vec")), Some((7, 34)));
+        assert_eq!(found(index.find(4, None, "debugPrintfEXT")), Some((5, 8)));
+    }
+
+    #[test]
+    fn find_falls_back_to_the_first_token_of_the_line() {
+        let index = index();
+        assert_eq!(found(index.find(6, None, "unknown")), Some((7, 8)));
+        assert_eq!(found(index.find(1, Some(200), "")), Some((2, 4)));
+        assert_eq!(found(index.find(20, None, "")), None);
+    }
+
+    #[test]
+    fn find_after_multi_line_tokens() {
+        let index = index();
+        assert_eq!(found(index.find(5, None, ")")), Some((6, 12)));
+        assert_eq!(found(index.find(5, Some(14), "")), Some((6, 13)));
+        assert_eq!(found(index.find(7, None, "}")), Some((8, 4)));
+    }
+
+    #[test]
+    fn span_at_offsets() {
+        let index = index();
+        let string = CODE_GROUP.find("\"first").unwrap() - 1;
+        assert_eq!(found(index.span_at(string)), Some((5, 23)));
+        assert_eq!(found(index.span_at(string + 15)), Some((5, 23)));
+        assert_eq!(found(index.span_at(0)), None);
+    }
+}
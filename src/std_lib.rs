@@ -0,0 +1,20 @@
+/// Prefix of the GLSL headers bundled with the crate: `#include <glsl_compiler/noise.glsl>`.
+pub const STD_LIB_PREFIX: &str = "glsl_compiler/";
+
+const STD_LIB: &[(&str, &str)] = &[
+    ("all.glsl", include_str!("../glsl/all.glsl")),
+    ("color.glsl", include_str!("../glsl/color.glsl")),
+    ("hash.glsl", include_str!("../glsl/hash.glsl")),
+    ("noise.glsl", include_str!("../glsl/noise.glsl")),
+    ("packing.glsl", include_str!("../glsl/packing.glsl")),
+    ("random.glsl", include_str!("../glsl/random.glsl")),
+];
+
+/// The bundled header `name`, without the `glsl_compiler/` prefix.
+pub fn std_lib_header(name: &str) -> Option<&'static str> {
+    STD_LIB.iter().find(|(header, _)| *header == name).map(|(_, content)| *content)
+}
+
+pub fn std_lib_header_names() -> Vec<&'static str> {
+    STD_LIB.iter().map(|(header, _)| *header).collect()
+}
@@ -0,0 +1,195 @@
+/// GLSL built-in types, variables and functions that are always in scope.
+const GLSL_BUILTINS: &[&str] = &[
+    // Types
+    "void", "bool", "int", "uint", "float", "double",
+    "vec2", "vec3", "vec4", "ivec2", "ivec3", "ivec4", "uvec2", "uvec3", "uvec4",
+    "bvec2", "bvec3", "bvec4", "dvec2", "dvec3", "dvec4",
+    "mat2", "mat3", "mat4", "mat2x2", "mat2x3", "mat2x4", "mat3x2", "mat3x3", "mat3x4", "mat4x2", "mat4x3", "mat4x4",
+    "sampler", "sampler1D", "sampler2D", "sampler3D", "samplerCube", "sampler2DArray", "sampler2DShadow",
+    "texture1D", "texture2D", "texture3D", "textureCube", "texture2DArray",
+    "image1D", "image2D", "image3D", "imageCube", "image2DArray", "iimage2D", "uimage2D", "iimage3D", "uimage3D",
+    "accelerationStructureEXT", "rayQueryEXT",
+
+    // Variables
+    "gl_GlobalInvocationID", "gl_LocalInvocationID", "gl_LocalInvocationIndex", "gl_WorkGroupID",
+    "gl_WorkGroupSize", "gl_NumWorkGroups",
+    "gl_Position", "gl_PointSize", "gl_ClipDistance", "gl_VertexIndex", "gl_InstanceIndex", "gl_DrawID",
+    "gl_FragCoord", "gl_FragDepth", "gl_FrontFacing", "gl_PointCoord", "gl_PrimitiveID", "gl_Layer",
+    "gl_SubgroupSize", "gl_SubgroupInvocationID", "gl_NumSubgroups", "gl_SubgroupID",
+    "gl_LaunchIDEXT", "gl_LaunchSizeEXT", "gl_WorldRayOriginEXT", "gl_WorldRayDirectionEXT",
+    "gl_ObjectRayOriginEXT", "gl_ObjectRayDirectionEXT", "gl_RayTminEXT", "gl_RayTmaxEXT", "gl_HitTEXT",
+    "gl_HitKindEXT", "gl_InstanceCustomIndexEXT", "gl_InstanceID", "gl_ObjectToWorldEXT", "gl_WorldToObjectEXT",
+    "gl_IncomingRayFlagsEXT", "gl_GeometryIndexEXT",
+    "gl_MeshVerticesEXT", "gl_MeshPrimitivesEXT", "gl_PrimitiveTriangleIndicesEXT",
+
+    // Math
+    "radians", "degrees", "sin", "cos", "tan", "asin", "acos", "atan", "sinh", "cosh", "tanh", "asinh", "acosh", "atanh",
+    "pow", "exp", "log", "exp2", "log2", "sqrt", "inversesqrt",
+    "abs", "sign", "floor", "trunc", "round", "roundEven", "ceil", "fract", "mod", "modf", "min", "max", "clamp",
+    "mix", "step", "smoothstep", "isnan", "isinf", "fma", "frexp", "ldexp",
+    "floatBitsToInt", "floatBitsToUint", "intBitsToFloat", "uintBitsToFloat",
+    "packUnorm2x16", "packSnorm2x16", "packUnorm4x8", "packSnorm4x8", "packHalf2x16",
+    "unpackUnorm2x16", "unpackSnorm2x16", "unpackUnorm4x8", "unpackSnorm4x8", "unpackHalf2x16",
+    "length", "distance", "dot", "cross", "normalize", "faceforward", "reflect", "refract",
+    "matrixCompMult", "outerProduct", "transpose", "determinant", "inverse",
+    "lessThan", "lessThanEqual", "greaterThan", "greaterThanEqual", "equal", "notEqual", "any", "all", "not",
+    "uaddCarry", "usubBorrow", "umulExtended", "imulExtended", "bitfieldExtract", "bitfieldInsert",
+    "bitfieldReverse", "bitCount", "findLSB", "findMSB",
+
+    // Textures and images
+    "textureSize", "textureQueryLod", "textureQueryLevels", "textureSamples", "texture", "textureProj", "textureLod",
+    "textureOffset", "texelFetch", "texelFetchOffset", "textureGrad", "textureGather", "textureGatherOffset",
+    "imageSize", "imageSamples", "imageLoad", "imageStore",
+    "imageAtomicAdd", "imageAtomicMin", "imageAtomicMax", "imageAtomicAnd", "imageAtomicOr", "imageAtomicXor",
+    "imageAtomicExchange", "imageAtomicCompSwap",
+
+    // Atomics, barriers and derivatives
+    "atomicAdd", "atomicMin", "atomicMax", "atomicAnd", "atomicOr", "atomicXor", "atomicExchange", "atomicCompSwap",
+    "barrier", "memoryBarrier", "memoryBarrierShared", "memoryBarrierBuffer", "memoryBarrierImage", "groupMemoryBarrier",
+    "dFdx", "dFdy", "fwidth", "dFdxFine", "dFdyFine", "dFdxCoarse", "dFdyCoarse",
+    "EmitVertex", "EndPrimitive",
+
+    // Subgroups
+    "subgroupBarrier", "subgroupElect", "subgroupAll", "subgroupAny", "subgroupBroadcast", "subgroupBroadcastFirst",
+    "subgroupBallot", "subgroupAdd", "subgroupMul", "subgroupMin", "subgroupMax", "subgroupAnd", "subgroupOr",
+    "subgroupXor", "subgroupShuffle", "subgroupShuffleXor",
+
+    // Ray tracing and mesh shading
+    "traceRayEXT", "reportIntersectionEXT", "executeCallableEXT", "ignoreIntersectionEXT", "terminateRayEXT",
+    "rayQueryInitializeEXT", "rayQueryProceedEXT", "SetMeshOutputsEXT", "EmitMeshTasksEXT",
+    "clockRealtime2x32EXT", "clockRealtimeEXT", "debugPrintfEXT",
+];
+
+const GLSL_KEYWORDS: &[&str] = &[
+    "return", "else", "if", "for", "while", "do", "switch", "case", "break", "continue", "discard",
+];
+
+/// Finds the closest identifier to `unknown`, declared in one of the `sources` or built into GLSL.
+pub fn suggest_identifier(unknown: &str, sources: &[&str]) -> Option<String> {
+    let mut candidates: Vec<String> = GLSL_BUILTINS.iter().map(|s| s.to_string()).collect();
+    for source in sources {
+        candidates.extend(declared_identifiers(source));
+    }
+
+    if candidates.iter().any(|c| c == unknown) {
+        return None;
+    }
+
+    let max_distance = (unknown.chars().count() / 3).max(1);
+    candidates.into_iter()
+        .map(|candidate| (edit_distance(unknown, &candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Identifiers that are declared in `source`: `#define NAME`, `<type> name` and block instance names `} name;`.
+fn declared_identifiers(source: &str) -> Vec<String> {
+    let mut identifiers = vec![];
+    let mut previous_ident: Option<&str> = None;
+    let mut previous_char = ' ';
+
+    let mut rest = source;
+    while let Some(c) = rest.chars().next() {
+        if rest.starts_with("//") {
+            rest = rest.find('\n').map(|i| &rest[i..]).unwrap_or("");
+            continue;
+        }
+        if rest.starts_with("/*") {
+            rest = rest.find("*/").map(|i| &rest[(i + 2)..]).unwrap_or("");
+            continue;
+        }
+
+        if c.is_alphabetic() || c == '_' {
+            let end = rest.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(rest.len());
+            let ident = &rest[..end];
+
+            let after_type = previous_ident.is_some_and(|p| !GLSL_KEYWORDS.contains(&p));
+            let after_block = previous_char == '}' && rest[end..].trim_start().starts_with(';');
+            if after_type || after_block {
+                identifiers.push(ident.to_string());
+            }
+
+            previous_ident = if previous_char == '#' && ident != "define" { None } else { Some(ident) };
+            previous_char = 'a';
+            rest = &rest[end..];
+            continue;
+        }
+
+        if !c.is_whitespace() {
+            previous_ident = None;
+            previous_char = c;
+        } else if c == '\n' && previous_char == '#' {
+            previous_char = ' ';
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+
+    identifiers
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + if ca == *cb { 0 } else { 1 };
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADER: &str = "\
+#define MAX_BOUNCES 4
+layout(binding = 0) uniform Params {
+    vec3 base_color;
+} params;
+// float hidden_value;
+float roughness_factor(vec2 uv) {
+    return uv.x;
+}
+";
+
+    #[test]
+    fn distances() {
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("imgaeStore", "imageStore"), 2);
+        assert_eq!(edit_distance("", "vec3"), 4);
+        assert_eq!(edit_distance("vec3", "vec3"), 0);
+    }
+
+    #[test]
+    fn closest_candidate_within_a_third_of_the_length() {
+        assert_eq!(suggest_identifier("normalise", &[]).as_deref(), Some("normalize"));
+        assert_eq!(suggest_identifier("smoothsteps", &[]).as_deref(), Some("smoothstep"));
+        assert_eq!(suggest_identifier("totally_unknown", &[]), None);
+        assert_eq!(suggest_identifier("normalize", &[]), None);
+    }
+
+    #[test]
+    fn builtins() {
+        assert_eq!(suggest_identifier("imgaeStore", &[]).as_deref(), Some("imageStore"));
+        assert_eq!(suggest_identifier("gl_GlobalInvocationId", &[]).as_deref(), Some("gl_GlobalInvocationID"));
+        assert_eq!(suggest_identifier("texelFecth", &[]).as_deref(), Some("texelFetch"));
+    }
+
+    #[test]
+    fn declared_in_the_sources() {
+        assert_eq!(suggest_identifier("base_colour", &[SHADER]).as_deref(), Some("base_color"));
+        assert_eq!(suggest_identifier("rougness_factor", &["", SHADER]).as_deref(), Some("roughness_factor"));
+        assert_eq!(suggest_identifier("MAX_BOUNCE", &[SHADER]).as_deref(), Some("MAX_BOUNCES"));
+        assert_eq!(suggest_identifier("paramz", &[SHADER]).as_deref(), Some("params"));
+        assert_eq!(suggest_identifier("hidden_valu", &[SHADER]), None);
+        assert_eq!(suggest_identifier("base_color", &[SHADER]), None);
+    }
+}
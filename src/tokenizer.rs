@@ -0,0 +1,90 @@
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TokenKind {
+    Ident,
+    Number,
+    Punct,
+    String,
+    Comment,
+}
+
+/// A token of GLSL source with its byte offset and 0-based line.
+#[derive(Clone, Copy, Debug)]
+pub struct GlslToken<'a> {
+    pub kind: TokenKind,
+    pub text: &'a str,
+    pub offset: usize,
+    pub line: usize,
+}
+
+impl<'a> GlslToken<'a> {
+    pub fn is(&self, text: &str) -> bool {
+        self.kind != TokenKind::Comment && self.kind != TokenKind::String && self.text == text
+    }
+}
+
+const MULTI_CHAR_PUNCTS: &[&str] = &[
+    "<<=", ">>=", "++", "--", "==", "!=", "<=", ">=", "&&", "||", "^^", "+=", "-=", "*=", "/=", "%=", "&=", "|=", "^=", "<<", ">>",
+];
+
+/// Splits GLSL source into tokens. Comments are kept as `TokenKind::Comment` tokens, whitespace is dropped.
+pub fn tokenize(source: &str) -> Vec<GlslToken<'_>> {
+    let mut tokens = vec![];
+    let bytes = source.as_bytes();
+    let mut offset = 0;
+    let mut line = 0;
+
+    while offset < source.len() {
+        let rest = &source[offset..];
+        let c = rest.chars().next().unwrap();
+
+        if c == '\n' {
+            line += 1;
+            offset += 1;
+            continue;
+        }
+        if c.is_whitespace() {
+            offset += c.len_utf8();
+            continue;
+        }
+
+        let (kind, len) = if rest.starts_with("//") {
+            let mut len = rest.find('\n').unwrap_or(rest.len());
+            // A line comment ending in a line continuation also comments out the next line.
+            while rest[..len].ends_with('\\') && len < rest.len() {
+                len += 1 + rest[(len + 1)..].find('\n').unwrap_or(rest.len() - len - 1);
+            }
+            (TokenKind::Comment, len)
+        } else if rest.starts_with("/*") {
+            (TokenKind::Comment, rest.find("*/").map(|i| i + 2).unwrap_or(rest.len()))
+        } else if c == '"' {
+            (TokenKind::String, rest[1..].find('"').map(|i| i + 2).unwrap_or(rest.len()))
+        } else if c.is_alphabetic() || c == '_' {
+            (TokenKind::Ident, rest.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(rest.len()))
+        } else if c.is_ascii_digit() || (c == '.' && bytes.get(offset + 1).is_some_and(|b| b.is_ascii_digit())) {
+            let mut len = 0;
+            let mut previous = ' ';
+            for c in rest.chars() {
+                let exponent_sign = (c == '-' || c == '+') && (previous == 'e' || previous == 'E') && !rest.starts_with("0x");
+                if !(c.is_alphanumeric() || c == '.' || exponent_sign) {
+                    break;
+                }
+                len += c.len_utf8();
+                previous = c;
+            }
+            (TokenKind::Number, len)
+        } else {
+            let len = MULTI_CHAR_PUNCTS.iter()
+                .find(|p| rest.starts_with(*p))
+                .map(|p| p.len())
+                .unwrap_or(c.len_utf8());
+            (TokenKind::Punct, len)
+        };
+
+        let text = &source[offset..(offset + len)];
+        tokens.push(GlslToken { kind, text, offset, line });
+        line += text.matches('\n').count();
+        offset += len;
+    }
+
+    tokens
+}
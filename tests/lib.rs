@@ -1,6 +1,8 @@
 #[macro_use]
 extern crate glsl_compiler;
 
+use glsl_compiler_runtime::{PrintfInfo, ProfileInfo, ProfileLayout};
+
 #[test]
 fn void_main_empty() {
     let (bin, _): (&[u8], &[&str]) = glsl!{type = Compute, code = {
@@ -36,6 +38,46 @@ fn shader() {
     println!("{:?}", bin)
 }
 
+#[test]
+fn glsl_include_rust_scheme() {
+    let (bin, _): (&[u8], &[&str]) = glsl!{type = Compute, code = {
+        #version 450 core
+
+        #include "rust:tests/lib.rs#included.glsl"
+        #include "rust:self#included-scheme.glsl"
+        #include "rust:self#code-first.glsl"
+        #include "shaders/test-hyphen.glsl"
+
+        layout(binding = 0, rgba8) uniform writeonly image2D img;
+        void main () {
+            uvec2 pos = gl_GlobalInvocationID.xy;
+            imageStore(img, ivec2(pos), COLOR + SCHEME_COLOR + HYPHEN_COLOR + CODE_FIRST_COLOR);
+        }
+    }};
+
+    println!("{:?}", bin)
+}
+
+#[allow(dead_code)]
+fn included_scheme() {
+    glsl!{type = Include, name = "included-scheme.glsl", code = {
+        #define SCHEME_COLOR vec4(pos, 0.0, 1.0)
+    }};
+}
+
+#[allow(dead_code)]
+fn included_code_first() {
+    let _ = "glsl!{type = Include, name = \"code-first.glsl\", code = {}}";
+    glsl!{
+        code = {
+            // } in a comment
+            #define CODE_FIRST_COLOR vec4(pos, 0.0, 1.0)
+        },
+        type = Include,
+        name = "code-first.glsl"
+    };
+}
+
 #[allow(dead_code)]
 fn included() {
     glsl!{type = Include, name = "included.glsl", code = {
@@ -93,7 +135,7 @@ fn glsl_file_include_in_include2() {
 
 #[test]
 fn glsl_file_profile() {
-    let (bin, profile_scope_names): (&[u8], &[&str]) = glsl!{type = Compute, profile, code = {
+    let (bin, profile_info): (&[u8], &ProfileInfo) = glsl!{type = Compute, profile, code = {
         #version 450 core
 
         #include "shaders/test_include_include2.glsl"
@@ -108,6 +150,691 @@ fn glsl_file_profile() {
     }};
 
     println!("{:?}", bin);
-    println!("{:?}", profile_scope_names);
+    println!("{:?}", profile_info);
+}
+
+
+#[test]
+fn glsl_lints_deny() {
+    let (bin, _): (&[u8], &[&str]) = glsl!{type = Compute, lints = deny, code = {
+        #version 450 core
+
+        layout(local_size_x = 8, local_size_y = 8) in;
+
+        layout(binding = 0, rgba8) uniform writeonly image2D img;
+        layout(binding = 1) uniform Params {
+            vec3 color; // glsl-lint: allow(std140_vec3)
+            float alpha;
+        } params;
+
+        void main () {
+            uvec2 pos = gl_GlobalInvocationID.xy;
+            imageStore(img, ivec2(pos), vec4(params.color, params.alpha));
+        }
+    }};
+
+    println!("{:?}", bin)
+}
+
+#[test]
+fn glsl_compile_fail() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/lints_*.rs");
+    cases.compile_fail("tests/ui/include_*.rs");
+}
+
+#[test]
+fn glsl_from_file_relative_to_source() {
+    let (bin, _): (&[u8], &[&str]) = glsl!{type = Compute, file = "../shaders/test.glsl"};
+
+    println!("{:?}", bin)
+}
+
+#[test]
+fn glsl_include_dirs() {
+    let (bin, _): (&[u8], &[&str]) = glsl!{type = Compute, include_dirs = ["shaders"], code = {
+        #version 450 core
+
+        #include <test_include_include2.glsl>
+
+        layout(binding = 0, rgba8) uniform writeonly image2D img;
+        void main () {
+            uvec2 pos = gl_GlobalInvocationID.xy;
+            imageStore(img, ivec2(pos), COLOR);
+        }
+    }};
+
+    println!("{:?}", bin)
+}
+
+#[test]
+fn glsl_include_pragma_once() {
+    let (bin, _): (&[u8], &[&str]) = glsl!{type = Compute, code = {
+        #version 450 core
+
+        #include "shaders/test_pragma_once.glsl"
+        #include "shaders/test_pragma_once_include.glsl"
+
+        layout(binding = 0, rgba8) uniform writeonly image2D img;
+        void main () {
+            uvec2 pos = gl_GlobalInvocationID.xy;
+            imageStore(img, ivec2(pos), pragma_once_color(pos) + pragma_once_color_inverted(pos));
+        }
+    }};
+
+    println!("{:?}", bin)
+}
+
+#[test]
+fn glsl_include_pragma_once_spellings() {
+    let (bin, _): (&[u8], &[&str]) = glsl!{type = Compute, code = {
+        #version 450 core
+
+        #include "shaders/test_pragma_once.glsl"
+        #include "./shaders/test_pragma_once.glsl"
+        #include "shaders/../shaders/test_pragma_once_include.glsl"
+
+        layout(binding = 0, rgba8) uniform writeonly image2D img;
+        void main () {
+            uvec2 pos = gl_GlobalInvocationID.xy;
+            imageStore(img, ivec2(pos), pragma_once_color(pos) + pragma_once_color_inverted(pos));
+        }
+    }};
+
+    println!("{:?}", bin)
+}
+
+#[test]
+fn glsl_profile_skips_disabled_includes() {
+    let (bin, profile_info): (&[u8], &ProfileInfo) = glsl!{type = Compute, profile, code = {
+        #version 450 core
+
+        // #include "shaders/missing.glsl"
+        /*
+        #include "shaders/missing.glsl"
+        */
+        #if 0
+        #include "shaders/missing.glsl"
+        #endif
+        #include "shaders/test_include_include2.glsl"
+
+        layout(binding = 0, rgba8) uniform writeonly image2D img;
+        void main () {
+            //PROFILE("main");
+
+            uvec2 pos = gl_GlobalInvocationID.xy;
+            imageStore(img, ivec2(pos), COLOR);
+        }
+    }};
+
+    println!("{:?}", bin);
+    println!("{:?}", profile_info);
 }
 
+#[test]
+fn glsl_profile_include_guard_cycle() {
+    let (bin, profile_info): (&[u8], &ProfileInfo) = glsl!{type = Compute, profile, max_include_depth = 8, code = {
+        #version 450 core
+
+        #include "shaders/test_guard_a.glsl"
+
+        layout(binding = 0, rgba8) uniform writeonly image2D img;
+        void main () {
+            //PROFILE("main");
+
+            uvec2 pos = gl_GlobalInvocationID.xy;
+            imageStore(img, ivec2(pos), GUARD_A_COLOR + GUARD_B_COLOR);
+        }
+    }};
+
+    println!("{:?}", bin);
+    println!("{:?}", profile_info);
+}
+
+#[test]
+fn glsl_std_lib_compute() {
+    let (bin, _): (&[u8], &[&str]) = glsl!{type = Compute, code = {
+        #version 450 core
+
+        #include <glsl_compiler/all.glsl>
+
+        layout(local_size_x = 8, local_size_y = 8) in;
+        layout(binding = 0, rgba8) uniform writeonly image2D img;
+        void main () {
+            uvec2 pos = gl_GlobalInvocationID.xy;
+            uint rng = rng_seed(pos, 0);
+            vec3 normal = unpack_normal(pack_normal(random_unit_vector(rng)));
+            vec3 color = hsv_to_rgb(vec3(fbm(vec2(pos) * 0.01, 4) * 0.5 + 0.5, 1.0, 1.0));
+            color *= random_cosine_hemisphere(normal, rng) * 0.5 + 0.5;
+            imageStore(img, ivec2(pos), linear_to_srgb(vec4(tonemap_aces(color), 1.0)));
+        }
+    }};
+
+    println!("{:?}", bin)
+}
+
+#[test]
+fn glsl_std_lib_vertex() {
+    let (bin, _): (&[u8], &[&str]) = glsl!{type = Vertex, code = {
+        #version 450 core
+
+        #include <glsl_compiler/hash.glsl>
+        #include <glsl_compiler/noise.glsl>
+
+        layout(location = 0) in vec3 position;
+        layout(location = 0) out vec3 color;
+        void main () {
+            float height = value_noise(position) + gradient_noise(position.xz) * fbm(position, 3);
+            color = hash33(position);
+            gl_Position = vec4(position + vec3(0.0, height, 0.0), 1.0);
+        }
+    }};
+
+    println!("{:?}", bin)
+}
+
+#[test]
+fn glsl_std_lib_fragment() {
+    let (bin, _): (&[u8], &[&str]) = glsl!{type = Fragment, code = {
+        #version 450 core
+
+        #include <glsl_compiler/color.glsl>
+        #include <glsl_compiler/packing.glsl>
+        #include <glsl_compiler/random.glsl>
+
+        layout(location = 0) in vec3 color;
+        layout(location = 0) out vec4 out_color;
+        layout(location = 1) out uint out_packed;
+        void main () {
+            uint rng = rng_seed(uvec2(gl_FragCoord.xy), 1);
+            vec3 hsv = rgb_to_hsv(srgb_to_linear(color)) + rng_next_vec3(rng) * 0.01;
+            vec3 rgb = tonemap_reinhard(hsv_to_rgb(hsv)) * luminance(color);
+            out_color = unpack_unorm_10_10_10_2(pack_unorm_10_10_10_2(vec4(rgb, 1.0)));
+            out_packed = pack_normal(oct_decode(oct_encode(normalize(color + 0.1))));
+        }
+    }};
+
+    println!("{:?}", bin)
+}
+
+#[test]
+fn glsl_std_lib_geometry() {
+    let (bin, _): (&[u8], &[&str]) = glsl!{type = Geometry, code = {
+        #version 450 core
+
+        #include <glsl_compiler/random.glsl>
+
+        layout(points) in;
+        layout(points, max_vertices = 4) out;
+        void main () {
+            uint rng = rng_seed(uvec2(gl_PrimitiveIDIn, 0), 2);
+            for (int i = 0; i < 4; i++) {
+                gl_Position = gl_in[0].gl_Position + vec4(random_unit_vector(rng) * rng_next_float(rng), 0.0);
+                EmitVertex();
+            }
+            EndPrimitive();
+        }
+    }};
+
+    println!("{:?}", bin)
+}
+
+#[test]
+fn glsl_std_lib_mesh() {
+    let (bin, _): (&[u8], &[&str]) = glsl!{type = Mesh, code = {
+        #version 460 core
+        #extension GL_EXT_mesh_shader : require
+
+        #include <glsl_compiler/noise.glsl>
+        #include <glsl_compiler/random.glsl>
+
+        layout(local_size_x = 1) in;
+        layout(triangles, max_vertices = 3, max_primitives = 1) out;
+        layout(location = 0) out vec3 color[];
+        void main () {
+            uint rng = rng_seed(gl_WorkGroupID.xy, 3);
+            SetMeshOutputsEXT(3, 1);
+            for (uint i = 0; i < 3; i++) {
+                vec3 position = random_unit_vector(rng);
+                gl_MeshVerticesEXT[i].gl_Position = vec4(position, 1.0);
+                color[i] = vec3(gradient_noise(position.xy) * 0.5 + 0.5);
+            }
+            gl_PrimitiveTriangleIndicesEXT[0] = uvec3(0, 1, 2);
+        }
+    }};
+
+    println!("{:?}", bin)
+}
+
+#[test]
+fn glsl_std_lib_ray_generation() {
+    let (bin, _): (&[u8], &[&str]) = glsl!{type = RayGeneration, code = {
+        #version 460 core
+        #extension GL_EXT_ray_tracing : require
+
+        #include <glsl_compiler/color.glsl>
+        #include <glsl_compiler/random.glsl>
+
+        layout(binding = 0) uniform accelerationStructureEXT scene;
+        layout(binding = 1, rgba8) uniform writeonly image2D img;
+        layout(location = 0) rayPayloadEXT vec3 payload;
+        void main () {
+            uint rng = rng_seed(gl_LaunchIDEXT.xy, 4);
+            vec2 uv = (vec2(gl_LaunchIDEXT.xy) + rng_next_vec2(rng)) / vec2(gl_LaunchSizeEXT.xy);
+            traceRayEXT(scene, gl_RayFlagsOpaqueEXT, 0xFF, 0, 0, 0, vec3(uv, 0.0), 0.001, vec3(0.0, 0.0, 1.0), 1000.0, 0);
+            imageStore(img, ivec2(gl_LaunchIDEXT.xy), linear_to_srgb(vec4(tonemap_aces(payload), 1.0)));
+        }
+    }};
+
+    println!("{:?}", bin)
+}
+
+#[test]
+fn glsl_std_lib_any_hit() {
+    let (bin, _): (&[u8], &[&str]) = glsl!{type = AnyHit, code = {
+        #version 460 core
+        #extension GL_EXT_ray_tracing : require
+
+        #include <glsl_compiler/hash.glsl>
+
+        hitAttributeEXT vec2 attributes;
+        void main () {
+            if (hash12(attributes + vec2(gl_PrimitiveID)) < 0.5) {
+                ignoreIntersectionEXT;
+            }
+        }
+    }};
+
+    println!("{:?}", bin)
+}
+
+#[test]
+fn glsl_std_lib_closest_hit() {
+    let (bin, _): (&[u8], &[&str]) = glsl!{type = ClosestHit, code = {
+        #version 460 core
+        #extension GL_EXT_ray_tracing : require
+
+        #include <glsl_compiler/all.glsl>
+
+        layout(location = 0) rayPayloadInEXT vec3 payload;
+        hitAttributeEXT vec2 attributes;
+        void main () {
+            uint rng = rng_seed(uvec2(gl_PrimitiveID, gl_InstanceCustomIndexEXT), 5);
+            vec3 normal = unpack_normal(pack_normal(oct_decode(attributes * 2.0 - 1.0)));
+            vec3 direction = random_cosine_hemisphere(normal, rng);
+            payload = hsv_to_rgb(vec3(fbm(direction, 3) * 0.5 + 0.5, 0.8, 1.0)) * max(dot(normal, direction), 0.0);
+        }
+    }};
+
+    println!("{:?}", bin)
+}
+
+#[test]
+fn glsl_std_lib_miss() {
+    let (bin, _): (&[u8], &[&str]) = glsl!{type = Miss, code = {
+        #version 460 core
+        #extension GL_EXT_ray_tracing : require
+
+        #include <glsl_compiler/color.glsl>
+        #include <glsl_compiler/noise.glsl>
+
+        layout(location = 0) rayPayloadInEXT vec3 payload;
+        void main () {
+            vec3 direction = normalize(gl_WorldRayDirectionEXT);
+            float clouds = fbm(direction * 4.0, 4) * 0.5 + 0.5;
+            payload = srgb_to_linear(mix(vec3(0.4, 0.6, 1.0), vec3(1.0), clouds * max(direction.y, 0.0)));
+        }
+    }};
+
+    println!("{:?}", bin)
+}
+
+#[test]
+fn glsl_include_dependency_root() {
+    let (bin, _): (&[u8], &[&str]) = glsl!{type = Fragment, code = {
+        #version 450 core
+
+        #include <render_core/lighting.glsl>
+
+        layout(location = 0) in vec3 normal;
+        layout(location = 0) out vec4 color;
+        void main () {
+            color = vec4(vec3(lambert(normalize(normal), vec3(0.0, 1.0, 0.0))), 1.0);
+        }
+    }};
+
+    println!("{:?}", bin)
+}
+
+#[test]
+fn glsl_profile_layout() {
+    let (bin, profile_info): (&[u8], &ProfileInfo) = glsl!{type = Compute, profile = { set = 3, in_binding = 0, out_binding = 1 }, code = {
+        #version 450 core
+
+        layout(binding = 0, rgba8) uniform writeonly image2D img;
+        void main () {
+            //PROFILE("main");
+
+            uvec2 pos = gl_GlobalInvocationID.xy;
+            imageStore(img, ivec2(pos), vec4(pos, 0.0, 1.0));
+        }
+    }};
+
+    println!("{:?}", bin);
+    assert_eq!(profile_info.names, &["main"]);
+    assert_eq!(profile_info.layout, ProfileLayout { set: 3, in_binding: Some(0), push_constant: false, out_binding: 1 });
+}
+
+#[test]
+fn glsl_profile_push_constant() {
+    let (bin, profile_info): (&[u8], &ProfileInfo) = glsl!{type = Compute, profile = { push_constant }, code = {
+        #version 450 core
+
+        layout(binding = 0, rgba8) uniform writeonly image2D img;
+        // layout(push_constant) uniform Commented { uint x; } commented;
+        void main () {
+            //PROFILE("main");
+
+            uvec2 pos = gl_GlobalInvocationID.xy;
+            imageStore(img, ivec2(pos), vec4(pos, 0.0, 1.0));
+        }
+    }};
+
+    println!("{:?}", bin);
+    assert_eq!(profile_info.layout, ProfileLayout { set: 0, in_binding: None, push_constant: true, out_binding: 11 });
+}
+
+#[test]
+fn glsl_profile_fragment() {
+    let (bin, profile_info): (&[u8], &ProfileInfo) = glsl!{type = Fragment, profile, code = {
+        #version 450 core
+
+        layout(location = 0) out vec4 color;
+        void main () {
+            //PROFILE("main");
+
+            color = vec4(gl_FragCoord.xy, 0.0, 1.0);
+        }
+    }};
+
+    println!("{:?}", bin);
+    assert_eq!(profile_info.names, &["main"]);
+}
+
+#[test]
+fn glsl_profile_vertex() {
+    let (bin, profile_info): (&[u8], &ProfileInfo) = glsl!{type = Vertex, profile, code = {
+        #version 450 core
+
+        void main () {
+            //PROFILE("main");
+
+            gl_Position = vec4(float(gl_VertexIndex), 0.0, 0.0, 1.0);
+        }
+    }};
+
+    println!("{:?}", bin);
+    assert_eq!(profile_info.names, &["main"]);
+}
+
+#[test]
+fn glsl_profile_mesh() {
+    let (bin, profile_info): (&[u8], &ProfileInfo) = glsl!{type = Mesh, profile, code = {
+        #version 460 core
+        #extension GL_EXT_mesh_shader : require
+
+        layout(local_size_x = 1) in;
+        layout(triangles, max_vertices = 3, max_primitives = 1) out;
+        void main () {
+            //PROFILE("main");
+
+            SetMeshOutputsEXT(3, 1);
+            for (uint i = 0; i < 3; i++) {
+                gl_MeshVerticesEXT[i].gl_Position = vec4(float(i), float(gl_WorkGroupID.x), 0.0, 1.0);
+            }
+            gl_PrimitiveTriangleIndicesEXT[0] = uvec3(0, 1, 2);
+        }
+    }};
+
+    println!("{:?}", bin);
+    assert_eq!(profile_info.names, &["main"]);
+}
+
+#[test]
+fn glsl_profile_raygen() {
+    let (bin, profile_info): (&[u8], &ProfileInfo) = glsl!{type = RayGeneration, profile, code = {
+        #version 460 core
+        #extension GL_EXT_ray_tracing : require
+
+        layout(binding = 0, rgba8) uniform writeonly image2D img;
+        void main () {
+            //PROFILE("main");
+
+            imageStore(img, ivec2(gl_LaunchIDEXT.xy), vec4(vec2(gl_LaunchIDEXT.xy) / vec2(gl_LaunchSizeEXT.xy), 0.0, 1.0));
+        }
+    }};
+
+    println!("{:?}", bin);
+    assert_eq!(profile_info.names, &["main"]);
+}
+
+#[test]
+fn glsl_profile_nested_scopes() {
+    let (bin, profile_info): (&[u8], &ProfileInfo) = glsl!{type = Compute, profile, code = {
+        #version 450 core
+
+        layout(binding = 0, rgba8) uniform writeonly image2D img;
+
+        vec4 shade(uvec2 pos) {
+            //PROFILE("shade");
+            return vec4(pos, 0.0, 1.0);
+        }
+
+        void main () {
+            //PROFILE("main");
+
+            uvec2 pos = gl_GlobalInvocationID.xy;
+            vec4 color = vec4(0.0);
+            for (uint i = 0; i < 4; i++) {
+                //PROFILE("loop");
+                color += shade(pos + i);
+            }
+            imageStore(img, ivec2(pos), color);
+        }
+    }};
+
+    println!("{:?}", bin);
+    assert_eq!(profile_info.names, &["shade", "main", "loop"]);
+    assert_eq!(profile_info.parents, &[None, None, Some(1)]);
+}
+
+#[test]
+fn glsl_profile_aggregate() {
+    let (bin, profile_info): (&[u8], &ProfileInfo) = glsl!{type = Compute, profile = aggregate, code = {
+        #version 450 core
+        layout(local_size_x = 8, local_size_y = 8) in;
+
+        layout(binding = 0, rgba8) uniform writeonly image2D img;
+        void main () {
+            //PROFILE("main");
+
+            uvec2 pos = gl_GlobalInvocationID.xy;
+            imageStore(img, ivec2(pos), vec4(pos, 0.0, 1.0));
+        }
+    }};
+
+    println!("{:?}", bin);
+    assert_eq!(profile_info.names, &["main"]);
+    assert!(profile_info.aggregate);
+    assert_eq!(profile_info.layout, ProfileLayout { set: 0, in_binding: None, push_constant: false, out_binding: 11 });
+}
+
+#[test]
+fn glsl_profile_aggregate_subgroup() {
+    let (bin, profile_info): (&[u8], &ProfileInfo) = glsl!{type = Compute, profile = { aggregate, subgroup, set = 1, out_binding = 0 }, code = {
+        #version 450 core
+        layout(local_size_x = 8, local_size_y = 8) in;
+
+        layout(binding = 0, rgba8) uniform writeonly image2D img;
+        void main () {
+            //PROFILE("main");
+
+            uvec2 pos = gl_GlobalInvocationID.xy;
+            if (pos.x % 2 == 0) {
+                //PROFILE("even");
+                imageStore(img, ivec2(pos), vec4(pos, 0.0, 1.0));
+            }
+        }
+    }};
+
+    println!("{:?}", bin);
+    assert_eq!(profile_info.names, &["main", "even"]);
+    assert_eq!(profile_info.parents, &[None, Some(0)]);
+    assert_eq!((profile_info.aggregate, profile_info.layout.set, profile_info.layout.out_binding), (true, 1, 0));
+}
+
+#[test]
+fn glsl_profile_exits() {
+    let (bin, profile_info): (&[u8], &ProfileInfo) = glsl!{type = Compute, profile, code = {
+        #version 450 core
+
+        layout(binding = 0, rgba8) uniform writeonly image2D img;
+
+        vec4 shade(uvec2 pos) {
+            //PROFILE("shade");
+            vec4 returnColor = vec4(pos, 0.0, 1.0); // return in a comment
+            if (pos.x == 0) return vec4(0.0);
+            return returnColor;
+        }
+
+        void main () {
+            //PROFILE("main");
+
+            uvec2 pos = gl_GlobalInvocationID.xy;
+            vec4 color = vec4(0.0);
+            for (uint i = 0; i < 4; i++) {
+                //PROFILE("loop");
+                if (i == 2) continue;
+                for (uint j = 0; j < 2; j++) {
+                    if (j == 1) break;
+                }
+                switch (i) {
+                    case 3: break;
+                    default: color += shade(pos + i);
+                }
+                if (color.x > 4.0) break; else if (color.y > 3.0) return;
+            }
+            imageStore(img, ivec2(pos), color);
+        }
+    }};
+
+    println!("{:?}", bin);
+    assert_eq!(profile_info.names, &["shade", "main", "loop"]);
+    assert_eq!(profile_info.parents, &[None, None, Some(1)]);
+}
+
+#[test]
+fn glsl_profile_functions() {
+    let (bin, profile_info): (&[u8], &ProfileInfo) = glsl!{type = Compute, profile = { functions = ["shade*", "!shade_debug"] }, code = {
+        #version 450 core
+        #define SQUARE(x) { x * x }
+
+        layout(binding = 0, rgba8) uniform writeonly image2D img;
+
+        float shade_base(uvec2 pos);
+
+        float shade_base(uvec2 pos) {
+            return float(pos.x);
+        }
+
+        vec4 shade_debug(uvec2 pos) {
+            return vec4(1.0, 0.0, 1.0, 1.0);
+        }
+
+        vec4 shade(uvec2 pos) {
+            //PROFILE("shade_body");
+            return vec4(shade_base(pos), 0.0, 0.0, 1.0);
+        }
+
+        float helper() {
+            return 1.0;
+        }
+
+        void main () {
+            uvec2 pos = gl_GlobalInvocationID.xy;
+            imageStore(img, ivec2(pos), shade(pos) * helper());
+        }
+    }};
+
+    println!("{:?}", bin);
+    assert_eq!(profile_info.names, &["shade_base", "shade_body", "main"]);
+    assert_eq!(profile_info.parents, &[None, None, None]);
+}
+
+#[test]
+fn glsl_profile_function_overloads() {
+    let (bin, profile_info): (&[u8], &ProfileInfo) = glsl!{type = Compute, profile = { functions = ["srgb_to_linear"] }, code = {
+        #version 450 core
+
+        #include <glsl_compiler/color.glsl>
+
+        layout(binding = 0, rgba8) uniform writeonly image2D img;
+        void main () {
+            uvec2 pos = gl_GlobalInvocationID.xy;
+            imageStore(img, ivec2(pos), srgb_to_linear(vec4(vec2(pos) / 64.0, 0.0, 1.0)));
+        }
+    }};
+
+    println!("{:?}", bin);
+    assert_eq!(profile_info.names, &["srgb_to_linear(vec3)", "srgb_to_linear(vec4)", "main"]);
+    assert_eq!(profile_info.parents, &[None, None, None]);
+}
+
+#[test]
+fn glsl_profile_counters() {
+    let (bin, profile_info): (&[u8], &ProfileInfo) = glsl!{type = Compute, profile, code = {
+        #version 450 core
+
+        layout(binding = 0, rgba8) uniform writeonly image2D img;
+        void main () {
+            //PROFILE("main");
+
+            uvec2 pos = gl_GlobalInvocationID.xy;
+            uint steps = 0;
+            for (uint i = 0; i < pos.x; i++) {
+                //COUNT("iterations"); // see f();
+                steps += i;
+                if (steps > 100) {
+                    //COUNT("early_outs");
+                    break;
+                }
+            }
+            COUNT("steps", steps);
+            imageStore(img, ivec2(pos), vec4(steps));
+        }
+    }};
+
+    println!("{:?}", bin);
+    assert_eq!(profile_info.names, &["main", "iterations", "early_outs", "steps"]);
+    assert_eq!(profile_info.counters, &[1, 2, 3]);
+}
+
+#[test]
+fn glsl_profile_printf() {
+    let (bin, profile_info): (&[u8], &ProfileInfo) = glsl!{type = Compute, profile = { printf, printf_binding = 3 }, code = {
+        #version 450 core
+
+        layout(binding = 0, rgba8) uniform writeonly image2D img;
+        void main () {
+            uvec2 pos = gl_GlobalInvocationID.xy;
+            printf("pos %v2u value %5.2f", pos, float(pos.x) * 0.5);
+            if (pos.x == 0)
+                printf("zero %d", int(pos.y));
+            imageStore(img, ivec2(pos), vec4(1.0));
+        }
+    }};
+
+    println!("{:?}", bin);
+    assert!(profile_info.names.is_empty());
+    assert_eq!(profile_info.printf, Some(PrintfInfo { binding: 3, record: 4, formats: &["pos %v2u value %5.2f", "zero %d"] }));
+}
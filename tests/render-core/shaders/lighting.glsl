@@ -0,0 +1,5 @@
+#pragma once
+
+float lambert(vec3 normal, vec3 light_direction) {
+    return max(dot(normal, light_direction), 0.0);
+}
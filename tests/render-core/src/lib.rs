@@ -0,0 +1 @@
+//! Test crate that publishes GLSL headers in `shaders/` through `[package.metadata.glsl_compiler] include_root`.
@@ -0,0 +1,17 @@
+use glsl_compiler::glsl;
+
+fn main() {
+    let _ = glsl!{type = Compute, profile, code = {
+        #version 450 core
+
+        #include "../../shaders/test_cycle_a.glsl"
+
+        layout(local_size_x = 8, local_size_y = 8) in;
+        layout(binding = 0, rgba8) uniform writeonly image2D img;
+        void main () {
+            //PROFILE("main");
+
+            imageStore(img, ivec2(gl_GlobalInvocationID.xy), CYCLE_A_COLOR + CYCLE_B_COLOR);
+        }
+    }};
+}
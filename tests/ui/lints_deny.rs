@@ -0,0 +1,28 @@
+use glsl_compiler::glsl;
+
+fn main() {
+    let (bin, _): (&[u8], &[&str]) = glsl!{type = Compute, lints = deny, code = {
+        #version 450 core
+        #extension GL_EXT_debug_printf : require
+
+        layout(binding = 0, rgba8) uniform writeonly image2D img;
+        uniform Params {
+            vec3 color;
+            float alpha;
+        } params;
+
+        // glsl-lint: allow(missing_binding)
+        uniform Suppressed {
+            vec3 offset; // glsl-lint: allow(std140_vec3)
+            float scale;
+        } suppressed;
+
+        void main () {
+            ivec2 pos = ivec2(gl_GlobalInvocationID.xy);
+            vec4 previous = imageLoad(img, pos);
+            imageStore(img, pos, previous + vec4(params.color + suppressed.offset, params.alpha * suppressed.scale));
+        }
+    }};
+
+    println!("{:?}", bin)
+}
@@ -0,0 +1,23 @@
+#![deny(deprecated)]
+
+use glsl_compiler::glsl;
+
+fn main() {
+    let (bin, _): (&[u8], &[&str]) = glsl!{type = Compute, lints = warn, code = {
+        #version 450 core
+
+        layout(local_size_x = 8, local_size_y = 8) in;
+
+        layout(binding = 0, rgba8) uniform writeonly image2D img;
+        layout(binding = 1) uniform Params {
+            vec3 color;
+            float alpha;
+        } params;
+
+        void main () {
+            imageStore(img, ivec2(gl_GlobalInvocationID.xy), vec4(params.color, params.alpha));
+        }
+    }};
+
+    println!("{:?}", bin)
+}